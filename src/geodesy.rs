@@ -0,0 +1,166 @@
+use crate::{Vector, Axis, GeographicArrayError};
+
+//the WGS84 ellipsoid, what GPS reports positions against
+pub const WGS84_SEMI_MAJOR_AXIS: f64 = 6378137.0;
+pub const WGS84_FLATTENING: f64 = 1.0 / 298.257223563;
+const WGS84_ECCENTRICITY_SQUARED: f64 = WGS84_FLATTENING * (2.0 - WGS84_FLATTENING);
+
+//a position on the WGS84 ellipsoid, latitude and longitude in degrees, altitude in meters above the ellipsoid
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Geodetic {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+impl Geodetic {
+    pub fn new(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        Self::try_new(latitude, longitude, altitude).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    //latitude has to be within +-90 and longitude within +-180
+    pub fn try_new(latitude: f64, longitude: f64, altitude: f64) -> Result<Self, GeographicArrayError> {
+        let geodetic = Self { latitude, longitude, altitude };
+        geodetic.validate()?;
+        Ok(geodetic)
+    }
+
+    pub fn validate(&self) -> Result<(), GeographicArrayError> {
+        if !(-90.0..=90.0).contains(&self.latitude) || !(-180.0..=180.0).contains(&self.longitude) || !self.altitude.is_finite() {
+            return Err(GeographicArrayError::InvalidGeodetic { latitude: self.latitude, longitude: self.longitude, altitude: self.altitude });
+        }
+        Ok(())
+    }
+}
+
+//meters from the centre of the earth, x through latitude 0 longitude 0, z through the north pole
+//what GNSS receivers and satellite ephemeris report
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ecef {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Ecef {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self::try_new(x, y, z).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(x: f64, y: f64, z: f64) -> Result<Self, GeographicArrayError> {
+        let ecef = Self { x, y, z };
+        ecef.validate()?;
+        Ok(ecef)
+    }
+
+    pub fn validate(&self) -> Result<(), GeographicArrayError> {
+        for (axis, value) in [(Axis::X, self.x), (Axis::Y, self.y), (Axis::Z, self.z)] {
+            if !value.is_finite() {
+                return Err(GeographicArrayError::NonFinite { axis, value });
+            }
+        }
+        Ok(())
+    }
+}
+
+//flat East North Up meters around an origin, x is east, y is north and z is up
+//the ground curves away below the plane, a point on the ellipsoid 65km from the origin has a z of about -330
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LocalFrame {
+    origin: Geodetic,
+    origin_ecef: Ecef,
+    sin_latitude: f64,
+    cos_latitude: f64,
+    sin_longitude: f64,
+    cos_longitude: f64,
+}
+
+impl LocalFrame {
+    pub fn new(origin: Geodetic) -> Self {
+        let (sin_latitude, cos_latitude) = origin.latitude.to_radians().sin_cos();
+        let (sin_longitude, cos_longitude) = origin.longitude.to_radians().sin_cos();
+        Self {
+            origin,
+            origin_ecef: geodetic_to_ecef(&origin),
+            sin_latitude,
+            cos_latitude,
+            sin_longitude,
+            cos_longitude,
+        }
+    }
+
+    pub fn origin(&self) -> &Geodetic {
+        &self.origin
+    }
+
+    //the vector isn't bounds checked, the array it is inserted into decides what to do with it
+    pub fn to_local(&self, geodetic: &Geodetic) -> Vector {
+        self.ecef_to_local(&geodetic_to_ecef(geodetic))
+    }
+
+    pub fn to_geodetic(&self, vector: &Vector) -> Geodetic {
+        ecef_to_geodetic(&self.local_to_ecef(vector))
+    }
+
+    //the offset from the origin turned to face east, north and up
+    pub fn ecef_to_local(&self, ecef: &Ecef) -> Vector {
+        let (dx, dy, dz) = (ecef.x - self.origin_ecef.x, ecef.y - self.origin_ecef.y, ecef.z - self.origin_ecef.z);
+        Vector {
+            x: -self.sin_longitude * dx + self.cos_longitude * dy,
+            y: -self.sin_latitude * self.cos_longitude * dx - self.sin_latitude * self.sin_longitude * dy + self.cos_latitude * dz,
+            z: self.cos_latitude * self.cos_longitude * dx + self.cos_latitude * self.sin_longitude * dy + self.sin_latitude * dz,
+        }
+    }
+
+    pub fn local_to_ecef(&self, vector: &Vector) -> Ecef {
+        //the rotation is orthogonal, so the inverse is its transpose
+        let (east, north, up) = (vector.x, vector.y, vector.z);
+        Ecef {
+            x: self.origin_ecef.x - self.sin_longitude * east - self.sin_latitude * self.cos_longitude * north + self.cos_latitude * self.cos_longitude * up,
+            y: self.origin_ecef.y + self.cos_longitude * east - self.sin_latitude * self.sin_longitude * north + self.cos_latitude * self.sin_longitude * up,
+            z: self.origin_ecef.z + self.cos_latitude * north + self.sin_latitude * up,
+        }
+    }
+
+    pub fn origin_ecef(&self) -> &Ecef {
+        &self.origin_ecef
+    }
+}
+
+pub fn geodetic_to_ecef(geodetic: &Geodetic) -> Ecef {
+    let (sin_latitude, cos_latitude) = geodetic.latitude.to_radians().sin_cos();
+    let (sin_longitude, cos_longitude) = geodetic.longitude.to_radians().sin_cos();
+    //the radius of curvature in the prime vertical
+    let n: f64 = WGS84_SEMI_MAJOR_AXIS / (1.0 - WGS84_ECCENTRICITY_SQUARED * sin_latitude * sin_latitude).sqrt();
+    Ecef {
+        x: (n + geodetic.altitude) * cos_latitude * cos_longitude,
+        y: (n + geodetic.altitude) * cos_latitude * sin_longitude,
+        z: (n * (1.0 - WGS84_ECCENTRICITY_SQUARED) + geodetic.altitude) * sin_latitude,
+    }
+}
+
+//iterates on the latitude, a handful of passes is well below a millimeter from the surface out to satellite orbits
+//the altitude is worked out in a way that doesn't divide by cos(latitude), so the poles are fine
+pub fn ecef_to_geodetic(ecef: &Ecef) -> Geodetic {
+    let Ecef { x, y, z } = *ecef;
+    let longitude: f64 = y.atan2(x);
+    let p: f64 = x.hypot(y);
+    let mut latitude: f64 = z.atan2(p * (1.0 - WGS84_ECCENTRICITY_SQUARED));
+    let mut altitude: f64 = 0.0;
+    for _ in 0..10 {
+        let (sin_latitude, cos_latitude) = latitude.sin_cos();
+        let n: f64 = WGS84_SEMI_MAJOR_AXIS / (1.0 - WGS84_ECCENTRICITY_SQUARED * sin_latitude * sin_latitude).sqrt();
+        altitude = p * cos_latitude + z * sin_latitude - WGS84_SEMI_MAJOR_AXIS * WGS84_SEMI_MAJOR_AXIS / n;
+        let next: f64 = z.atan2(p * (1.0 - WGS84_ECCENTRICITY_SQUARED * n / (n + altitude)));
+        if (next - latitude).abs() < 1e-15 {
+            latitude = next;
+            break;
+        }
+        latitude = next;
+    }
+    Geodetic {
+        latitude: latitude.to_degrees(),
+        longitude: longitude.to_degrees(),
+        altitude,
+    }
+}
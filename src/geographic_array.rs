@@ -1,64 +1,325 @@
-use crate::{Vector, IndexVector, coordinate_to_index_x, coordinate_to_index_y, coordinate_to_index_z, Axis, DynamicSearchValidated, Candidates, SearchMode};
+use crate::{Vector, IndexVector, Axis, DynamicSearchValidated, Candidates, Candidate, QueryResult, SearchMode, AxisRange, NearestIter, EntryId, Category, Zone, GeographicArrayError, QueryOptions, AxisSelection, OutOfBoundsPolicy, Bounds, GeographicArrayConfig, invalidate_by_filter, metric::{DistanceMetric, Ground}, geodesy::{Geodetic, Ecef, LocalFrame}, grid::{Utm, Mgrs, GeohashCell, encode_geohash, utm_zone}};
 
 use {
-    crate::{ReferenceVector, ZONES_USIZE},
-    std::{collections::BTreeMap, rc::Rc, time::Instant, vec},
+    crate::ReferenceVector,
+    std::{cell::Cell, rc::Rc, time::Instant, vec},
 };
 
-pub struct GeographicArray {
-    pub x: Vec<Vec<ReferenceVector>>,
+//everything known about a stored point, the ReferenceVectors in the zones point back here through their EntryId
+struct Entry<T> {
+    vector: Vector,
+    index_vector: Option<IndexVector>,  //None while the point is in the overflow bucket
+    category: Category,
+    value: T,
+}
+
+//entry is None once removed, generation is bumped on every removal so stale EntryIds don't match the next occupant
+struct Slot<T> {
+    generation: u32,
+    entry: Option<Entry<T>>,
+}
+
+//T is the value stored alongside each point
+pub struct GeographicArray<T> {
+    pub x: Vec<Zone>,
     //_x_median_index: usize,
-    pub y: Vec<Vec<ReferenceVector>>,
+    pub y: Vec<Zone>,
     //_y_median_index: usize,
-    pub z: Vec<Vec<ReferenceVector>>,
+    pub z: Vec<Zone>,
     //_z_median_index: usize,
+    config: GeographicArrayConfig,
+    overflow: Zone,     //points outside the zones, only used by OutOfBoundsPolicy::Overflow
+    out_of_bounds_policy: OutOfBoundsPolicy,
+    geodetic_origin: Option<LocalFrame>,   //what latitude and longitude are converted against, see with_geodetic_origin
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<usize>,
+    len: usize,
 }
 
-impl GeographicArray {
+impl<T> GeographicArray<T> {
+    //the default bounds with this many zones on each axis
     pub fn new(zones: usize) -> Self {
-        Self {
-            x: vec![Vec::new(); zones],
-            //_x_median_index: zones / 2,
-            y: vec![Vec::new(); zones],
-            //_y_median_index: zones / 2,
-            z: vec![Vec::new(); zones],
-            //_z_median_index: zones / 2,
-        }
+        Self::with_config(GeographicArrayConfig::new(Bounds::default(), zones))
     }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
-        Self {
-            x: vec![Vec::new(); ZONES_USIZE],
-            //_x_median_index: ZONES_USIZE / 2,
-            y: vec![Vec::new(); ZONES_USIZE],
-            //_y_median_index: ZONES_USIZE / 2,
-            z: vec![Vec::new(); ZONES_USIZE],
-            //_z_median_index: ZONES_USIZE / 2,
-        }
-    }
-
-    pub fn insert(&mut self, vector: Vector) -> IndexVector {
-        let x_normalised_index: usize = coordinate_to_index_x(vector.x);
-        let y_normalised_index: usize = coordinate_to_index_y(vector.y);
-        let z_normalised_index: usize = coordinate_to_index_z(vector.z);
-        let x_ref = Rc::new(vector.x);
-        let y_ref = Rc::new(vector.y);
-        let z_ref = Rc::new(vector.z);
-        self.x[x_normalised_index].push(ReferenceVector::new_real_x(
+        Self::with_config(GeographicArrayConfig::default())
+    }
+
+    pub fn with_config(config: GeographicArrayConfig) -> Self {
+        Self::try_with_config(config).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_with_config(config: GeographicArrayConfig) -> Result<Self, GeographicArrayError> {
+        config.validate()?;
+        Ok(Self {
+            x: vec![Zone::default(); config.zones_x],
+            //_x_median_index: config.zones_x / 2,
+            y: vec![Zone::default(); config.zones_y],
+            //_y_median_index: config.zones_y / 2,
+            z: vec![Zone::default(); config.zones_z],
+            //_z_median_index: config.zones_z / 2,
+            config,
+            overflow: Zone::default(),
+            out_of_bounds_policy: OutOfBoundsPolicy::default(),
+            geodetic_origin: None,
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            len: 0,
+        })
+    }
+
+    //only affects points inserted or moved afterwards
+    pub fn with_out_of_bounds_policy(mut self, out_of_bounds_policy: OutOfBoundsPolicy) -> Self {
+        self.out_of_bounds_policy = out_of_bounds_policy;
+        self
+    }
+
+    //vectors become East North Up meters around this latitude, longitude and altitude, x is east, y is north and z is up
+    //the _geodetic methods convert to and from it, and every QueryHit can give its latitude and longitude
+    pub fn with_geodetic_origin(self, latitude: f64, longitude: f64, altitude: f64) -> Self {
+        self.try_with_geodetic_origin(latitude, longitude, altitude).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_with_geodetic_origin(mut self, latitude: f64, longitude: f64, altitude: f64) -> Result<Self, GeographicArrayError> {
+        self.geodetic_origin = Some(LocalFrame::new(Geodetic::try_new(latitude, longitude, altitude)?));
+        Ok(self)
+    }
+
+    pub fn geodetic_origin(&self) -> Option<&LocalFrame> {
+        self.geodetic_origin.as_ref()
+    }
+
+    pub fn config(&self) -> &GeographicArrayConfig {
+        &self.config
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.config.bounds
+    }
+
+    pub fn out_of_bounds_policy(&self) -> OutOfBoundsPolicy {
+        self.out_of_bounds_policy
+    }
+
+    //the points stored outside the zones, every query looks through all of them
+    pub fn overflow(&self) -> &Zone {
+        &self.overflow
+    }
+
+    pub fn zones(&self, axis: &Axis) -> &Vec<Zone> {
+        match axis {
+            Axis::X => &self.x,
+            Axis::Y => &self.y,
+            Axis::Z => &self.z,
+        }
+    }
+
+    pub fn insert(&mut self, vector: Vector, value: T) -> EntryId {
+        self.insert_with_category(vector, value, Category::default())
+    }
+
+    //the point is placed in the category's group within each of its zones
+    pub fn insert_with_category(&mut self, vector: Vector, value: T, category: Category) -> EntryId {
+        self.try_insert_with_category(vector, value, category).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    //returns an error rather than panicking if the vector is out of bounds or not finite, nothing is stored in that case
+    pub fn try_insert(&mut self, vector: Vector, value: T) -> Result<EntryId, GeographicArrayError> {
+        self.try_insert_with_category(vector, value, Category::default())
+    }
+
+    pub fn try_insert_with_category(&mut self, vector: Vector, value: T, category: Category) -> Result<EntryId, GeographicArrayError> {
+        let index_vector: Option<IndexVector> = self.placement(&vector)?;
+        let id: EntryId = match self.free_slots.pop() {
+            Some(index) => EntryId::new(index, self.slots[index].generation),
+            None => {
+                self.slots.push(Slot { generation: 0, entry: None });
+                EntryId::new(self.slots.len() - 1, 0)
+            },
+        };
+        self.place(&vector, index_vector.as_ref(), category, id);
+        self.slots[id.index()].entry = Some(Entry {
+            vector,
+            index_vector,
+            category,
+            value,
+        });
+        self.len += 1;
+        Ok(id)
+    }
+
+    //the zones a point belongs in on each axis, None if it goes in the overflow bucket
+    fn placement(&self, vector: &Vector) -> Result<Option<IndexVector>, GeographicArrayError> {
+        vector.validate()?;
+        match self.out_of_bounds_policy {
+            OutOfBoundsPolicy::Reject => self.config.bounds.validate(vector)?,
+            OutOfBoundsPolicy::Clamp => {},
+            OutOfBoundsPolicy::Overflow => if !self.config.bounds.contains(vector) {
+                return Ok(None);
+            },
+        }
+        Ok(Some(self.config.index_vector(vector)))
+    }
+
+    //adds the point to its zone on each axis, or to the overflow bucket
+    fn place(&mut self, vector: &Vector, index_vector: Option<&IndexVector>, category: Category, id: EntryId) {
+        let index_vector: &IndexVector = match index_vector {
+            Some(index_vector) => index_vector,
+            None => {
+                self.overflow.push(category, ReferenceVector::new(vector.x, vector.y, vector.z, id));
+                return;
+            },
+        };
+        let x_ref = Rc::new(Cell::new(vector.x));
+        let y_ref = Rc::new(Cell::new(vector.y));
+        let z_ref = Rc::new(Cell::new(vector.z));
+        self.x[index_vector.x].push(category, ReferenceVector::new_real_x(
             vector.x,
             y_ref.clone(),
             z_ref.clone(),
+            id,
         ));
-        self.y[y_normalised_index].push(ReferenceVector::new_real_y(
+        self.y[index_vector.y].push(category, ReferenceVector::new_real_y(
             x_ref.clone(),
             vector.y,
             z_ref,
+            id,
         ));
-        self.z[z_normalised_index].push(ReferenceVector::new_real_z(
+        self.z[index_vector.z].push(category, ReferenceVector::new_real_z(
             x_ref,
             y_ref,
             vector.z,
+            id,
         ));
-        IndexVector::new(x_normalised_index, y_normalised_index, z_normalised_index)
+    }
+
+    //the opposite of place
+    fn displace(&mut self, index_vector: Option<&IndexVector>, category: Category, id: EntryId) {
+        match index_vector {
+            Some(index_vector) => for (zones, index) in [(&mut self.x, index_vector.x), (&mut self.y, index_vector.y), (&mut self.z, index_vector.z)] {
+                zones[index].remove(category, id);
+            },
+            None => {
+                self.overflow.remove(category, id);
+            },
+        }
+    }
+
+    //takes the point out of its zone on all three axes, returns what was stored
+    pub fn remove(&mut self, id: EntryId) -> Option<(EntryId, Vector, T)> {
+        let entry: &Entry<T> = self.entry(id)?;
+        let (index_vector, category): (Option<IndexVector>, Category) = (entry.index_vector.clone(), entry.category);
+        self.displace(index_vector.as_ref(), category, id);
+        let slot: &mut Slot<T> = &mut self.slots[id.index()];
+        let entry: Entry<T> = slot.entry.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(id.index());
+        self.len -= 1;
+        Some((id, entry.vector, entry.value))
+    }
+
+    //moves a point, only the axes where the zone changed have their element moved to another zone
+    //the coordinates are otherwise written in place, the shared coordinates are updated for every axis at once
+    //returns false if the entry doesn't exist
+    pub fn update_position(&mut self, id: EntryId, vector: Vector) -> bool {
+        match self.try_update_position(id, vector) {
+            Ok(()) => true,
+            Err(GeographicArrayError::UnknownEntry(_)) => false,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    //the entry is left where it was if the new vector is rejected by the OutOfBoundsPolicy or not finite
+    //moving into or out of the overflow bucket takes the point out and puts it back
+    pub fn try_update_position(&mut self, id: EntryId, vector: Vector) -> Result<(), GeographicArrayError> {
+        let new_index_vector: Option<IndexVector> = self.placement(&vector)?;
+        let (old_index_vector, category): (Option<IndexVector>, Category) = match self.entry(id) {
+            Some(entry) => (entry.index_vector.clone(), entry.category),
+            None => return Err(GeographicArrayError::UnknownEntry(id)),
+        };
+        if let (Some(old_index_vector), Some(new_index_vector)) = (&old_index_vector, &new_index_vector) {
+            self.move_between_zones(id, category, old_index_vector, new_index_vector, &vector);
+        } else {
+            self.displace(old_index_vector.as_ref(), category, id);
+            self.place(&vector, new_index_vector.as_ref(), category, id);
+        }
+        if let Some(entry) = self.slots[id.index()].entry.as_mut() {
+            entry.vector = vector;
+            entry.index_vector = new_index_vector;
+        }
+        Ok(())
+    }
+
+    fn move_between_zones(&mut self, id: EntryId, category: Category, old_index_vector: &IndexVector, new_index_vector: &IndexVector, vector: &Vector) {
+        for (zones, old_index, new_index) in [
+            (&mut self.x, old_index_vector.x, new_index_vector.x),
+            (&mut self.y, old_index_vector.y, new_index_vector.y),
+            (&mut self.z, old_index_vector.z, new_index_vector.z),
+        ] {
+            if let Some(reference_vector) = zones[old_index].get_mut(category, id) {
+                reference_vector.set(vector);
+            }
+            if old_index != new_index {
+                if let Some(reference_vector) = zones[old_index].remove(category, id) {
+                    zones[new_index].push(category, reference_vector);
+                }
+            }
+        }
+    }
+
+    //removes a point stored at exactly these coordinates, if there are duplicates only one of them is removed
+    //points outside the bounds are looked for in the edge zone and the overflow bucket, whichever policy stored them
+    pub fn remove_exact(&mut self, vector: &Vector) -> Option<(EntryId, Vector, T)> {
+        vector.validate().ok()?;
+        let id: EntryId = self.x[self.config.clamped_index(&Axis::X, vector.x)]
+            .iter()
+            .chain(self.overflow.iter())
+            .find(|reference_vector| reference_vector.x() == vector.x && reference_vector.y() == vector.y && reference_vector.z() == vector.z)?
+            .id();
+        self.remove(id)
+    }
+
+    fn entry(&self, id: EntryId) -> Option<&Entry<T>> {
+        match self.slots.get(id.index()) {
+            Some(slot) if slot.generation == id.generation() => slot.entry.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, id: EntryId) -> Option<&Vector> {
+        self.entry(id).map(|entry| &entry.vector)
+    }
+
+    pub fn get_value(&self, id: EntryId) -> Option<&T> {
+        self.entry(id).map(|entry| &entry.value)
+    }
+
+    //the zones the entry was placed in on each axis, None for points in the overflow bucket
+    pub fn get_index_vector(&self, id: EntryId) -> Option<&IndexVector> {
+        self.entry(id).and_then(|entry| entry.index_vector.as_ref())
+    }
+
+    pub fn get_category(&self, id: EntryId) -> Option<Category> {
+        self.entry(id).map(|entry| entry.category)
+    }
+
+    pub fn contains(&self, id: EntryId) -> bool {
+        self.entry(id).is_some()
+    }
+
+    //the value stored alongside a point returned from any of the searches, None if it has since been removed
+    pub fn value_of(&self, reference_vector: &ReferenceVector) -> Option<&T> {
+        self.get_value(reference_vector.id())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
     //TODO: Make the range in KM relative to real distances rather than indexes
@@ -68,22 +329,94 @@ impl GeographicArray {
     pub fn find_nearest(
         &self,
         nearest_to: &Vector,
-    ) -> Candidates {
-        let x_axis: &Axis = &Axis::X;
-        let y_axis: &Axis = &Axis::Y;
-        let z_axis: &Axis = &Axis::Z;
+    ) -> QueryResult<'_, T> {
+        self.find_nearest_with(nearest_to, &QueryOptions::new())
+    }
+
+    //returns an error rather than panicking if nearest_to is not finite, searching from outside the bounds is allowed
+    pub fn try_find_nearest(
+        &self,
+        nearest_to: &Vector,
+    ) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        nearest_to.validate()?;
+        Ok(self.find_nearest(nearest_to))
+    }
 
-        let nearest_to_index_vector = IndexVector::from_vector(nearest_to);
+    //only points the filter accepts are considered, the search carries on past zones holding nothing but rejected points
+    pub fn find_nearest_filtered<F: Fn(&Vector, &T) -> bool>(
+        &self,
+        nearest_to: &Vector,
+        filter: F,
+    ) -> QueryResult<'_, T> {
+        self.find_nearest_with(nearest_to, &QueryOptions::new().filter(filter))
+    }
 
-        let x_dynamic_search_order = DynamicSearchValidated::new(x_axis, nearest_to, nearest_to_index_vector.x, SearchMode::Nearest);
-        let y_dynamic_search_order = DynamicSearchValidated::new(y_axis, nearest_to, nearest_to_index_vector.y, SearchMode::Nearest);
-        let z_dynamic_search_order = DynamicSearchValidated::new(z_axis, nearest_to, nearest_to_index_vector.z, SearchMode::Nearest);
-        let mut candidates: Candidates = BTreeMap::new();
-        x_dynamic_search_order.run(self, &mut candidates);
-        y_dynamic_search_order.run(self, &mut candidates);
-        z_dynamic_search_order.run(self, &mut candidates);
-        
-        candidates
+    //only points in these categories are considered
+    pub fn find_nearest_in_categories(
+        &self,
+        nearest_to: &Vector,
+        categories: &[Category],
+    ) -> QueryResult<'_, T> {
+        self.find_nearest_with(nearest_to, &QueryOptions::new().categories(categories))
+    }
+
+    //distances are measured, and limited by DISTANCE_THRESHOLD, with the metric
+    pub fn find_nearest_with_metric<M: DistanceMetric + 'static>(
+        &self,
+        nearest_to: &Vector,
+        metric: M,
+    ) -> QueryResult<'_, T> {
+        self.find_nearest_with(nearest_to, &QueryOptions::new().metric(metric))
+    }
+
+    //nothing further than the options' max distance is returned, DISTANCE_THRESHOLD if it doesn't give one
+    //Auto walks X, Y and Z in turn
+    pub fn find_nearest_with(
+        &self,
+        nearest_to: &Vector,
+        options: &QueryOptions<'_, T>,
+    ) -> QueryResult<'_, T> {
+        let nearest_to_index_vector = self.config.index_vector(nearest_to);
+        let mut candidates: Candidates = Candidates::new();
+        for axis in options.axes(&[Axis::X, Axis::Y, Axis::Z]) {
+            DynamicSearchValidated::new(&self.config, &axis, nearest_to, nearest_to_index_vector.index(&axis), SearchMode::Nearest)
+                .with_categories(options.categories.as_deref())
+                .with_metric(options.metric.clone())
+                .with_max_distance(options.max_distance)
+                .run(self, &mut candidates, options.filter.as_ref());
+        }
+        self.result(candidates, options)
+    }
+
+    //ranks and limits by distance along the ground, a 40m difference in floor height doesn't push a point further away
+    //max_altitude_difference, if given, leaves out points more than that many meters above or below nearest_to
+    //the first hit is always the nearest along the ground within DISTANCE_THRESHOLD, the rest are the others within it that were visited on the way
+    //X is walked, walking Z would visit points in altitude order which says nothing about how far away they are on the ground
+    pub fn find_nearest_ground(
+        &self,
+        nearest_to: &Vector,
+        max_altitude_difference: Option<f64>,
+    ) -> QueryResult<'_, T> {
+        self.find_nearest_ground_with(nearest_to, max_altitude_difference, &QueryOptions::new())
+    }
+
+    //the options' metric is ignored, distance is always measured along the ground
+    pub fn find_nearest_ground_with(
+        &self,
+        nearest_to: &Vector,
+        max_altitude_difference: Option<f64>,
+        options: &QueryOptions<'_, T>,
+    ) -> QueryResult<'_, T> {
+        let nearest_to_index_vector = self.config.index_vector(nearest_to);
+        let mut candidates: Candidates = Candidates::new();
+        for axis in options.axes(&[Axis::X]) {
+            DynamicSearchValidated::new(&self.config, &axis, nearest_to, nearest_to_index_vector.index(&axis), SearchMode::Ground(max_altitude_difference))
+                .with_categories(options.categories.as_deref())
+                .with_metric(Rc::new(Ground))
+                .with_max_distance(options.max_distance)
+                .run(self, &mut candidates, options.filter.as_ref());
+        }
+        self.result(candidates, options)
     }
 
     //the axis chosen shouldn't actually matter, at this point, I believe the chosen axis is arbitrary if a full search of the axis is acceptable
@@ -91,26 +424,610 @@ impl GeographicArray {
         &self,
         nearest_to: &Vector,
         preferred_axis_of_search: &Axis,
-    ) -> Candidates {
-        let nearest_to_index_vector = IndexVector::from_vector(nearest_to);
-        let mut candidates: Candidates = BTreeMap::new();
+    ) -> QueryResult<'_, T> {
+        let filter = |_: &Vector, _: &T| true;
+        let nearest_to_index_vector = self.config.index_vector(nearest_to);
+        let mut candidates: Candidates = Candidates::new();
         match preferred_axis_of_search {
             Axis::X => {
-                let x_dynamic_search_order = DynamicSearchValidated::new(&Axis::X, nearest_to, nearest_to_index_vector.x, SearchMode::Nearest);
-                x_dynamic_search_order.run(self, &mut candidates);
+                let x_dynamic_search_order = DynamicSearchValidated::new(&self.config, &Axis::X, nearest_to, nearest_to_index_vector.x, SearchMode::Nearest);
+                x_dynamic_search_order.run(self, &mut candidates, &filter);
             },
             Axis::Y => {
-                let y_dynamic_search_order = DynamicSearchValidated::new(&Axis::Y, nearest_to, nearest_to_index_vector.y, SearchMode::Nearest);
-                y_dynamic_search_order.run(self, &mut candidates);
+                let y_dynamic_search_order = DynamicSearchValidated::new(&self.config, &Axis::Y, nearest_to, nearest_to_index_vector.y, SearchMode::Nearest);
+                y_dynamic_search_order.run(self, &mut candidates, &filter);
             },
             Axis::Z => {
-                let z_dynamic_search_order = DynamicSearchValidated::new(&Axis::Z, nearest_to, nearest_to_index_vector.z, SearchMode::Nearest);
-                z_dynamic_search_order.run(self, &mut candidates);
+                let z_dynamic_search_order = DynamicSearchValidated::new(&self.config, &Axis::Z, nearest_to, nearest_to_index_vector.z, SearchMode::Nearest);
+                z_dynamic_search_order.run(self, &mut candidates, &filter);
             },
         }
-        candidates
+        QueryResult::from_candidates(self, candidates)
     }
 
+    //returns exactly the k stored points closest to nearest_to, ordered by distance, fewer if the structure holds less than k
+    //there is no distance threshold, the search keeps widening until nothing unseen could be closer
+    //the X axis is walked, points are usually spread furthest horizontally so its rings hold the fewest
+    pub fn find_k_nearest(
+        &self,
+        nearest_to: &Vector,
+        k: usize,
+    ) -> QueryResult<'_, T> {
+        self.find_k_nearest_with(nearest_to, k, &QueryOptions::new())
+    }
+
+    pub fn try_find_k_nearest(
+        &self,
+        nearest_to: &Vector,
+        k: usize,
+    ) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        nearest_to.validate()?;
+        Ok(self.find_k_nearest(nearest_to, k))
+    }
+
+    //the k closest points the filter accepts
+    pub fn find_k_nearest_filtered<F: Fn(&Vector, &T) -> bool>(
+        &self,
+        nearest_to: &Vector,
+        k: usize,
+        filter: F,
+    ) -> QueryResult<'_, T> {
+        self.find_k_nearest_with(nearest_to, k, &QueryOptions::new().filter(filter))
+    }
+
+    //the k closest points in these categories
+    pub fn find_k_nearest_in_categories(
+        &self,
+        nearest_to: &Vector,
+        k: usize,
+        categories: &[Category],
+    ) -> QueryResult<'_, T> {
+        self.find_k_nearest_with(nearest_to, k, &QueryOptions::new().categories(categories))
+    }
+
+    //the k closest by the metric, still exact, the metric decides when nothing unseen could be closer
+    pub fn find_k_nearest_with_metric<M: DistanceMetric + 'static>(
+        &self,
+        nearest_to: &Vector,
+        k: usize,
+        metric: M,
+    ) -> QueryResult<'_, T> {
+        self.find_k_nearest_with(nearest_to, k, &QueryOptions::new().metric(metric))
+    }
+
+    //the k closest within the options' max distance, fewer if there aren't k that close, max_results lowers k
+    pub fn find_k_nearest_with(
+        &self,
+        nearest_to: &Vector,
+        k: usize,
+        options: &QueryOptions<'_, T>,
+    ) -> QueryResult<'_, T> {
+        let k: usize = options.max_results.map_or(k, |max_results| k.min(max_results));
+        let nearest_to_index_vector = self.config.index_vector(nearest_to);
+        let mut candidates: Candidates = Candidates::new();
+        for axis in options.axes(&[Axis::X]) {
+            DynamicSearchValidated::new(&self.config, &axis, nearest_to, nearest_to_index_vector.index(&axis), SearchMode::KNearest(k))
+                .with_categories(options.categories.as_deref())
+                .with_metric(options.metric.clone())
+                .with_max_distance(options.max_distance)
+                .run(self, &mut candidates, options.filter.as_ref());
+        }
+        QueryResult::from_candidates(self, candidates)
+    }
+
+    //yields every stored point in increasing distance from nearest_to, only visiting zones as more points are asked for
+    //useful when the number of points needed isn't known up front
+    pub fn nearest_iter(
+        &self,
+        nearest_to: &Vector,
+    ) -> NearestIter<'_, T> {
+        self.nearest_iter_with(nearest_to, QueryOptions::new())
+    }
+
+    pub fn try_nearest_iter(
+        &self,
+        nearest_to: &Vector,
+    ) -> Result<NearestIter<'_, T>, GeographicArrayError> {
+        nearest_to.validate()?;
+        Ok(self.nearest_iter(nearest_to))
+    }
+
+    //only yields points the filter accepts
+    pub fn nearest_iter_filtered<'a, F: Fn(&Vector, &T) -> bool + 'a>(
+        &'a self,
+        nearest_to: &Vector,
+        filter: F,
+    ) -> NearestIter<'a, T> {
+        self.nearest_iter_with(nearest_to, QueryOptions::new().filter(filter))
+    }
+
+    //only yields points in these categories
+    pub fn nearest_iter_in_categories(
+        &self,
+        nearest_to: &Vector,
+        categories: &[Category],
+    ) -> NearestIter<'_, T> {
+        self.nearest_iter_with(nearest_to, QueryOptions::new().categories(categories))
+    }
+
+    //yields points in increasing distance by the metric
+    pub fn nearest_iter_with_metric<M: DistanceMetric + 'static>(
+        &self,
+        nearest_to: &Vector,
+        metric: M,
+    ) -> NearestIter<'_, T> {
+        self.nearest_iter_with(nearest_to, QueryOptions::new().metric(metric))
+    }
+
+    //the options are taken by value, the iterator keeps the filter for as long as it lives
+    //stops at the options' max distance and after max_results points, if given
+    pub fn nearest_iter_with<'a>(
+        &'a self,
+        nearest_to: &Vector,
+        options: QueryOptions<'a, T>,
+    ) -> NearestIter<'a, T> {
+        let axis: Axis = options.axes(&[Axis::X])[0];
+        let nearest_to_index_vector = self.config.index_vector(nearest_to);
+        DynamicSearchValidated::new(&self.config, &axis, nearest_to, nearest_to_index_vector.index(&axis), SearchMode::All)
+            .with_categories(options.categories.as_deref())
+            .with_metric(options.metric)
+            .with_max_distance(options.max_distance)
+            .iter(self, options.filter)
+            .with_max_results(options.max_results)
+    }
+
+    //returns every stored point within radius meters of centre, ordered by distance
+    //only one axis is walked, the one where the radius covers the fewest zones, every zone it covers is checked
+    pub fn find_within_radius(
+        &self,
+        centre: &Vector,
+        radius: f64,
+    ) -> QueryResult<'_, T> {
+        self.find_within_radius_with(centre, radius, &QueryOptions::new())
+    }
+
+    //the radius must also be finite and not negative
+    pub fn try_find_within_radius(
+        &self,
+        centre: &Vector,
+        radius: f64,
+    ) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        centre.validate()?;
+        if !radius.is_finite() || radius < 0.0 {
+            return Err(GeographicArrayError::InvalidRadius(radius));
+        }
+        Ok(self.find_within_radius(centre, radius))
+    }
+
+    //every point within radius meters of centre that the filter accepts
+    pub fn find_within_radius_filtered<F: Fn(&Vector, &T) -> bool>(
+        &self,
+        centre: &Vector,
+        radius: f64,
+        filter: F,
+    ) -> QueryResult<'_, T> {
+        self.find_within_radius_with(centre, radius, &QueryOptions::new().filter(filter))
+    }
+
+    //every point within radius meters of centre in these categories
+    pub fn find_within_radius_in_categories(
+        &self,
+        centre: &Vector,
+        radius: f64,
+        categories: &[Category],
+    ) -> QueryResult<'_, T> {
+        self.find_within_radius_with(centre, radius, &QueryOptions::new().categories(categories))
+    }
+
+    //every point within radius of centre by the metric, the zones walked are sized by how far the metric lets a point reach on each axis
+    pub fn find_within_radius_with_metric<M: DistanceMetric + 'static>(
+        &self,
+        centre: &Vector,
+        radius: f64,
+        metric: M,
+    ) -> QueryResult<'_, T> {
+        self.find_within_radius_with(centre, radius, &QueryOptions::new().metric(metric))
+    }
+
+    //a max distance smaller than the radius shrinks it, max_results keeps the closest
+    pub fn find_within_radius_with(
+        &self,
+        centre: &Vector,
+        radius: f64,
+        options: &QueryOptions<'_, T>,
+    ) -> QueryResult<'_, T> {
+        let metric: &dyn DistanceMetric = options.metric.as_ref();
+        let radius: f64 = options.max_distance.map_or(radius, |max_distance| radius.min(max_distance));
+        let centre_index_vector = self.config.index_vector(centre);
+        let (axis, index) = select_axis(&self.config, options, [
+            (Axis::X, centre_index_vector.x, self.config.radius_to_index_range(&Axis::X, centre, metric.axis_extent(&Axis::X, radius))),
+            (Axis::Y, centre_index_vector.y, self.config.radius_to_index_range(&Axis::Y, centre, metric.axis_extent(&Axis::Y, radius))),
+            (Axis::Z, centre_index_vector.z, self.config.radius_to_index_range(&Axis::Z, centre, metric.axis_extent(&Axis::Z, radius))),
+        ]);
+        let mut candidates: Candidates = Candidates::new();
+        DynamicSearchValidated::new(&self.config, &axis, centre, index, SearchMode::Radius(radius))
+            .with_categories(options.categories.as_deref())
+            .with_metric(options.metric.clone())
+            .run(self, &mut candidates, options.filter.as_ref());
+        self.result(candidates, options)
+    }
+
+    //returns every stored point inside the axis aligned box between min and max, edges included, ordered by distance from the centre of the box
+    //only the zones of the most selective axis between min and max are walked
+    pub fn find_in_box(
+        &self,
+        min: &Vector,
+        max: &Vector,
+    ) -> QueryResult<'_, T> {
+        self.find_in_box_with(min, max, &QueryOptions::new())
+    }
+
+    pub fn try_find_in_box(
+        &self,
+        min: &Vector,
+        max: &Vector,
+    ) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        min.validate()?;
+        max.validate()?;
+        Ok(self.find_in_box(min, max))
+    }
+
+    //every point inside the box that the filter accepts
+    pub fn find_in_box_filtered<F: Fn(&Vector, &T) -> bool>(
+        &self,
+        min: &Vector,
+        max: &Vector,
+        filter: F,
+    ) -> QueryResult<'_, T> {
+        self.find_in_box_with(min, max, &QueryOptions::new().filter(filter))
+    }
+
+    //every point inside the box in these categories
+    pub fn find_in_box_in_categories(
+        &self,
+        min: &Vector,
+        max: &Vector,
+        categories: &[Category],
+    ) -> QueryResult<'_, T> {
+        self.find_in_box_with(min, max, &QueryOptions::new().categories(categories))
+    }
+
+    //distances from the centre of the box are measured with the options' metric, points further than the max distance from it are left out
+    pub fn find_in_box_with(
+        &self,
+        min: &Vector,
+        max: &Vector,
+        options: &QueryOptions<'_, T>,
+    ) -> QueryResult<'_, T> {
+        //the box can reach outside the structure, so the centre isn't bounds checked
+        let centre = Vector { x: (min.x + max.x) / 2.0, y: (min.y + max.y) / 2.0, z: (min.z + max.z) / 2.0 };
+        let centre_index_vector = self.config.index_vector(&centre);
+        let (axis, index) = select_axis(&self.config, options, [
+            (Axis::X, centre_index_vector.x, self.config.bounds_to_index_range(&Axis::X, min.x, max.x)),
+            (Axis::Y, centre_index_vector.y, self.config.bounds_to_index_range(&Axis::Y, min.y, max.y)),
+            (Axis::Z, centre_index_vector.z, self.config.bounds_to_index_range(&Axis::Z, min.z, max.z)),
+        ]);
+        let (positive, negative) = match axis {
+            Axis::X => (max.x, min.x),
+            Axis::Y => (max.y, min.y),
+            Axis::Z => (max.z, min.z),
+        };
+        let range_search = DynamicSearchValidated::new(&self.config, &axis, &centre, index, SearchMode::Range(positive, negative)).with_categories(options.categories.as_deref());
+        let max_distance: f64 = options.max_distance.unwrap_or(f64::INFINITY);
+        let mut candidates: Candidates = Candidates::new();
+        let mut potential_candidates: Vec<ReferenceVector> = range_search.collect(self);
+        invalidate_by_filter(self, options.filter.as_ref(), &mut potential_candidates);
+        for reference_vector in potential_candidates {
+            if reference_vector.x() >= min.x && reference_vector.x() <= max.x
+                && reference_vector.y() >= min.y && reference_vector.y() <= max.y
+                && reference_vector.z() >= min.z && reference_vector.z() <= max.z
+            {
+                let distance: f64 = options.metric.distance(&Vector::from_reference_vector(&reference_vector), &centre);
+                if distance <= max_distance {
+                    candidates.insert(Candidate::new(distance, &reference_vector));
+                }
+            }
+        }
+        self.result(candidates, options)
+    }
+
+    //the vector of a latitude, longitude and altitude in this array's frame, not bounds checked
+    pub fn geodetic_to_vector(&self, geodetic: &Geodetic) -> Result<Vector, GeographicArrayError> {
+        geodetic.validate()?;
+        Ok(self.geodetic_origin.as_ref().ok_or(GeographicArrayError::NoGeodeticOrigin)?.to_local(geodetic))
+    }
+
+    pub fn vector_to_geodetic(&self, vector: &Vector) -> Result<Geodetic, GeographicArrayError> {
+        Ok(self.geodetic_origin.as_ref().ok_or(GeographicArrayError::NoGeodeticOrigin)?.to_geodetic(vector))
+    }
+
+    //the vector of a point given in meters from the centre of the earth, not bounds checked
+    pub fn ecef_to_vector(&self, ecef: &Ecef) -> Result<Vector, GeographicArrayError> {
+        ecef.validate()?;
+        Ok(self.geodetic_origin.as_ref().ok_or(GeographicArrayError::NoGeodeticOrigin)?.ecef_to_local(ecef))
+    }
+
+    pub fn vector_to_ecef(&self, vector: &Vector) -> Result<Ecef, GeographicArrayError> {
+        Ok(self.geodetic_origin.as_ref().ok_or(GeographicArrayError::NoGeodeticOrigin)?.local_to_ecef(vector))
+    }
+
+    //the grid reference is taken through latitude and longitude, so it doesn't matter which UTM zone the origin is in
+    pub fn utm_to_vector(&self, utm: &Utm, altitude: f64) -> Result<Vector, GeographicArrayError> {
+        self.geodetic_to_vector(&utm.to_geodetic(altitude))
+    }
+
+    //in the UTM zone the point itself is in
+    pub fn vector_to_utm(&self, vector: &Vector) -> Result<Utm, GeographicArrayError> {
+        Utm::from_geodetic(&self.vector_to_geodetic(vector)?)
+    }
+
+    //the centre of the MGRS square
+    pub fn mgrs_to_vector(&self, mgrs: &Mgrs, altitude: f64) -> Result<Vector, GeographicArrayError> {
+        self.geodetic_to_vector(&mgrs.to_geodetic(altitude)?)
+    }
+
+    pub fn vector_to_mgrs(&self, vector: &Vector, precision: u8) -> Result<Mgrs, GeographicArrayError> {
+        Mgrs::from_geodetic(&self.vector_to_geodetic(vector)?, precision)
+    }
+
+    pub fn vector_to_geohash(&self, vector: &Vector, length: usize) -> Result<String, GeographicArrayError> {
+        encode_geohash(&self.vector_to_geodetic(vector)?, length)
+    }
+
+    //the centre of the geohash cell
+    pub fn geohash_to_vector(&self, geohash: &str, altitude: f64) -> Result<Vector, GeographicArrayError> {
+        self.geodetic_to_vector(&GeohashCell::decode(geohash)?.centre(altitude))
+    }
+
+    //panics if the array has no geodetic origin, or on anything insert would panic on
+    pub fn insert_geodetic(&mut self, geodetic: &Geodetic, value: T) -> EntryId {
+        self.try_insert_geodetic(geodetic, value).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_insert_geodetic(&mut self, geodetic: &Geodetic, value: T) -> Result<EntryId, GeographicArrayError> {
+        let vector: Vector = self.geodetic_to_vector(geodetic)?;
+        self.try_insert(vector, value)
+    }
+
+    pub fn insert_ecef(&mut self, ecef: &Ecef, value: T) -> EntryId {
+        self.try_insert_ecef(ecef, value).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_insert_ecef(&mut self, ecef: &Ecef, value: T) -> Result<EntryId, GeographicArrayError> {
+        let vector: Vector = self.ecef_to_vector(ecef)?;
+        self.try_insert(vector, value)
+    }
+
+    pub fn insert_utm(&mut self, utm: &Utm, altitude: f64, value: T) -> EntryId {
+        self.try_insert_utm(utm, altitude, value).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_insert_utm(&mut self, utm: &Utm, altitude: f64, value: T) -> Result<EntryId, GeographicArrayError> {
+        let vector: Vector = self.utm_to_vector(utm, altitude)?;
+        self.try_insert(vector, value)
+    }
+
+    pub fn insert_mgrs(&mut self, mgrs: &Mgrs, altitude: f64, value: T) -> EntryId {
+        self.try_insert_mgrs(mgrs, altitude, value).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_insert_mgrs(&mut self, mgrs: &Mgrs, altitude: f64, value: T) -> Result<EntryId, GeographicArrayError> {
+        let vector: Vector = self.mgrs_to_vector(mgrs, altitude)?;
+        self.try_insert(vector, value)
+    }
+
+    //None if the entry doesn't exist or the array has no geodetic origin
+    pub fn get_geodetic(&self, id: EntryId) -> Option<Geodetic> {
+        self.vector_to_geodetic(self.get(id)?).ok()
+    }
+
+    //find_nearest from a latitude, longitude and altitude, the hits carry theirs, see QueryHit::geodetic
+    pub fn find_nearest_geodetic(&self, nearest_to: &Geodetic) -> QueryResult<'_, T> {
+        self.try_find_nearest_geodetic(nearest_to).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_nearest_geodetic(&self, nearest_to: &Geodetic) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.find_nearest_geodetic_with(nearest_to, &QueryOptions::new())
+    }
+
+    pub fn find_nearest_geodetic_with(&self, nearest_to: &Geodetic, options: &QueryOptions<'_, T>) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        let vector: Vector = self.geodetic_to_vector(nearest_to)?;
+        Ok(self.find_nearest_with(&vector, options))
+    }
+
+    //find_k_nearest from a latitude, longitude and altitude
+    pub fn find_k_nearest_geodetic(&self, nearest_to: &Geodetic, k: usize) -> QueryResult<'_, T> {
+        self.try_find_k_nearest_geodetic(nearest_to, k).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_k_nearest_geodetic(&self, nearest_to: &Geodetic, k: usize) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.try_find_k_nearest(&self.geodetic_to_vector(nearest_to)?, k)
+    }
+
+    //find_within_radius around a latitude, longitude and altitude, the radius is straight line meters
+    pub fn find_within_radius_geodetic(&self, centre: &Geodetic, radius: f64) -> QueryResult<'_, T> {
+        self.try_find_within_radius_geodetic(centre, radius).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_within_radius_geodetic(&self, centre: &Geodetic, radius: f64) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.try_find_within_radius(&self.geodetic_to_vector(centre)?, radius)
+    }
+
+    //every stored point whose latitude and longitude are between the two corners, edges included, at any altitude
+    //the box can't cross 180 east, a west edge further east than the east edge finds nothing
+    pub fn find_in_box_geodetic(&self, south_west: &Geodetic, north_east: &Geodetic) -> QueryResult<'_, T> {
+        self.try_find_in_box_geodetic(south_west, north_east).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_in_box_geodetic(&self, south_west: &Geodetic, north_east: &Geodetic) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        south_west.validate()?;
+        north_east.validate()?;
+        let (south, west, north, east) = (south_west.latitude, south_west.longitude, north_east.latitude, north_east.longitude);
+        self.try_find_in_region(
+            (south, west),
+            (north, east),
+            &|latitude, longitude, altitude| Geodetic { latitude, longitude, altitude },
+            &|geodetic| (south..=north).contains(&geodetic.latitude) && (west..=east).contains(&geodetic.longitude),
+        )
+    }
+
+    //find_nearest from a grid reference, the hits give theirs with QueryHit::{utm(), mgrs()}
+    pub fn find_nearest_utm(&self, nearest_to: &Utm, altitude: f64) -> QueryResult<'_, T> {
+        self.try_find_nearest_utm(nearest_to, altitude).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_nearest_utm(&self, nearest_to: &Utm, altitude: f64) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.try_find_nearest_geodetic(&nearest_to.to_geodetic(altitude))
+    }
+
+    pub fn find_nearest_mgrs(&self, nearest_to: &Mgrs, altitude: f64) -> QueryResult<'_, T> {
+        self.try_find_nearest_mgrs(nearest_to, altitude).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_nearest_mgrs(&self, nearest_to: &Mgrs, altitude: f64) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.try_find_nearest_geodetic(&nearest_to.to_geodetic(altitude)?)
+    }
+
+    pub fn find_k_nearest_utm(&self, nearest_to: &Utm, altitude: f64, k: usize) -> QueryResult<'_, T> {
+        self.try_find_k_nearest_utm(nearest_to, altitude, k).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_k_nearest_utm(&self, nearest_to: &Utm, altitude: f64, k: usize) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.try_find_k_nearest_geodetic(&nearest_to.to_geodetic(altitude), k)
+    }
+
+    pub fn find_k_nearest_mgrs(&self, nearest_to: &Mgrs, altitude: f64, k: usize) -> QueryResult<'_, T> {
+        self.try_find_k_nearest_mgrs(nearest_to, altitude, k).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_k_nearest_mgrs(&self, nearest_to: &Mgrs, altitude: f64, k: usize) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.try_find_k_nearest_geodetic(&nearest_to.to_geodetic(altitude)?, k)
+    }
+
+    pub fn find_within_radius_utm(&self, centre: &Utm, altitude: f64, radius: f64) -> QueryResult<'_, T> {
+        self.try_find_within_radius_utm(centre, altitude, radius).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_within_radius_utm(&self, centre: &Utm, altitude: f64, radius: f64) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.try_find_within_radius_geodetic(&centre.to_geodetic(altitude), radius)
+    }
+
+    pub fn find_within_radius_mgrs(&self, centre: &Mgrs, altitude: f64, radius: f64) -> QueryResult<'_, T> {
+        self.try_find_within_radius_mgrs(centre, altitude, radius).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_within_radius_mgrs(&self, centre: &Mgrs, altitude: f64, radius: f64) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        self.try_find_within_radius_geodetic(&centre.to_geodetic(altitude)?, radius)
+    }
+
+    //every stored point whose easting and northing in min's zone are between the two references, edges included, at any altitude
+    //both have to be in the same zone and hemisphere
+    pub fn find_in_box_utm(&self, min: &Utm, max: &Utm) -> QueryResult<'_, T> {
+        self.try_find_in_box_utm(min, max).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_in_box_utm(&self, min: &Utm, max: &Utm) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        if (min.zone, min.hemisphere) != (max.zone, max.hemisphere) {
+            return Err(GeographicArrayError::InvalidGridReference(format!("UTM box {} to {}", min, max)));
+        }
+        let (zone, hemisphere) = (min.zone, min.hemisphere);
+        self.try_find_in_region(
+            (min.easting, min.northing),
+            (max.easting, max.northing),
+            &|easting, northing, altitude| Utm { zone, hemisphere, easting, northing }.to_geodetic(altitude),
+            &|geodetic| Utm::from_geodetic_in_zone(geodetic, zone).is_ok_and(|utm| {
+                utm.hemisphere == hemisphere && (min.easting..=max.easting).contains(&utm.easting) && (min.northing..=max.northing).contains(&utm.northing)
+            }),
+        )
+    }
+
+    //every stored point in the MGRS square at its precision, at any altitude, ordered by distance from the middle of the square
+    //squares at the edge of a zone are cut off by it, points past the edge have a reference in the next zone
+    pub fn find_in_mgrs(&self, square: &Mgrs) -> QueryResult<'_, T> {
+        self.try_find_in_mgrs(square).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_in_mgrs(&self, square: &Mgrs) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        let size: f64 = 10f64.powi(5 - square.precision as i32);
+        let south_west: Utm = Mgrs {
+            easting: (square.easting / size).floor() * size,
+            northing: (square.northing / size).floor() * size,
+            ..*square
+        }.to_utm()?;
+        let (zone, hemisphere) = (south_west.zone, south_west.hemisphere);
+        let (min, max) = ((south_west.easting, south_west.northing), (south_west.easting + size, south_west.northing + size));
+        self.try_find_in_region(
+            min,
+            max,
+            &|easting, northing, altitude| Utm { zone, hemisphere, easting, northing }.to_geodetic(altitude),
+            &|geodetic| utm_zone(geodetic.latitude, geodetic.longitude) == zone && Utm::from_geodetic_in_zone(geodetic, zone).is_ok_and(|utm| {
+                utm.hemisphere == hemisphere && (min.0..max.0).contains(&utm.easting) && (min.1..max.1).contains(&utm.northing)
+            }),
+        )
+    }
+
+    //every stored point whose latitude and longitude are inside the geohash cell, at any altitude, ordered by distance from the middle of the cell
+    pub fn find_in_geohash(&self, prefix: &str) -> QueryResult<'_, T> {
+        self.try_find_in_geohash(prefix).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_in_geohash(&self, prefix: &str) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        let cell: GeohashCell = GeohashCell::decode(prefix)?;
+        self.try_find_in_region(
+            (cell.south, cell.west),
+            (cell.north, cell.east),
+            &|latitude, longitude, altitude| Geodetic { latitude, longitude, altitude },
+            &|geodetic| cell.contains(geodetic),
+        )
+    }
+
+    //a region drawn as the rectangle min to max in flat coordinates, latitude and longitude or easting and northing, at any altitude
+    //the outline is mapped to a box around it in the local frame, then only points in the box are converted back and checked with contains
+    fn try_find_in_region(
+        &self,
+        min: (f64, f64),
+        max: (f64, f64),
+        to_geodetic: &dyn Fn(f64, f64, f64) -> Geodetic,
+        contains: &dyn Fn(&Geodetic) -> bool,
+    ) -> Result<QueryResult<'_, T>, GeographicArrayError> {
+        let local_frame: &LocalFrame = self.geodetic_origin.as_ref().ok_or(GeographicArrayError::NoGeodeticOrigin)?;
+
+        //the edges curve in the local frame and lean with altitude, so they are walked at the lowest and highest altitude the array holds
+        //the furthest any edge reaches can fall between the points walked, the margin covers it
+        const STEPS: usize = 16;
+        let (z_min, z_max) = self.config.bounds.axis(&Axis::Z);
+        let (mut low, mut high) = (Vector { x: f64::INFINITY, y: f64::INFINITY, z: f64::MIN }, Vector { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY, z: f64::MAX });
+        for altitude in [local_frame.origin().altitude + z_min, local_frame.origin().altitude + z_max] {
+            for step in 0..=STEPS {
+                let along: f64 = step as f64 / STEPS as f64;
+                let first: f64 = min.0 + (max.0 - min.0) * along;
+                let second: f64 = min.1 + (max.1 - min.1) * along;
+                for (first, second) in [(first, min.1), (first, max.1), (min.0, second), (max.0, second)] {
+                    let vector: Vector = local_frame.to_local(&to_geodetic(first, second, altitude));
+                    low.x = low.x.min(vector.x);
+                    low.y = low.y.min(vector.y);
+                    high.x = high.x.max(vector.x);
+                    high.y = high.y.max(vector.y);
+                }
+            }
+        }
+        let margin: f64 = 10.0 + (high.x - low.x).max(high.y - low.y) / 100.0;
+        (low.x, low.y, high.x, high.y) = (low.x - margin, low.y - margin, high.x + margin, high.y + margin);
+
+        let options = QueryOptions::new().filter(|vector: &Vector, _: &T| contains(&local_frame.to_geodetic(vector)));
+        Ok(self.find_in_box_with(&low, &high, &options))
+    }
+
+    //keeps the closest max_results, if the options give one
+    fn result(&self, mut candidates: Candidates, options: &QueryOptions<'_, T>) -> QueryResult<'_, T> {
+        if let Some(max_results) = options.max_results {
+            candidates.truncate(max_results);
+        }
+        QueryResult::from_candidates(self, candidates)
+    }
+}
+
+impl GeographicArray<()> {
     pub fn run(&mut self) -> u128 {
         let start_time = Instant::now();
         println!(
@@ -119,7 +1036,7 @@ impl GeographicArray {
         );
         let mut rng = rand::thread_rng();
         let synthetic_value: Vector = Vector::generate_random_seeded(&mut rng);
-        self.insert(synthetic_value.clone());
+        self.insert(synthetic_value.clone(), ());
         let values_to_insert: usize = 10000000;
         println!(
             "{}: Inserting a few values: {}",
@@ -127,7 +1044,7 @@ impl GeographicArray {
             values_to_insert,
         );
         for _ in 0..values_to_insert {
-            self.insert(Vector::generate_random_seeded(&mut rng));
+            self.insert(Vector::generate_random_seeded(&mut rng), ());
         }
         println!(
             "{}: Nearest to random synthetic value: X: {}, Y: {}, Z: {}",
@@ -140,14 +1057,14 @@ impl GeographicArray {
         //move to testing.rs
         let near_candidates = self.find_nearest(&synthetic_value);
         println!("Found {} candidates.", near_candidates.len());
-        for (cumulative_distance, coordinate) in near_candidates {
+        for hit in near_candidates {
             println!(
                 "{}: Distance: {:17}, X: {}, Y: {}, Z: {}",
                 start_time.elapsed().as_micros(),
-                cumulative_distance,
-                coordinate.x(),
-                coordinate.y(),
-                coordinate.z()
+                hit.distance(),
+                hit.vector().x,
+                hit.vector().y,
+                hit.vector().z
             );
         }
 
@@ -169,7 +1086,7 @@ impl GeographicArray {
             
             assert_eq!(ordered_candidates.len(), ordered_candidates_experimental.len());
 
-            for (cumulative_distance, reference_vector) in ordered_candidates.iter() {
+            for hit in ordered_candidates.iter() {
                 println!(
                     "{}: Nearest to random value: X: {}, Y: {}, Z: {}",
                     start_time.elapsed().as_micros(),
@@ -180,21 +1097,21 @@ impl GeographicArray {
                 println!(
                     "{}: 3-axis,      distance: {:17}, X: {}, Y: {}, Z: {}",
                     start_time.elapsed().as_micros(),
-                    cumulative_distance,
-                    reference_vector.x(),
-                    reference_vector.y(),
-                    reference_vector.z()
+                    hit.distance(),
+                    hit.vector().x,
+                    hit.vector().y,
+                    hit.vector().z
                 );
             }
 
-            for (cumulative_distance, reference_vector) in ordered_candidates_experimental.iter() {
+            for hit in ordered_candidates_experimental.iter() {
                 println!(
                     "{}: single-axis, distance: {:17}, X: {}, Y: {}, Z: {}",
                     start_time.elapsed().as_micros(),
-                    cumulative_distance,
-                    reference_vector.x(),
-                    reference_vector.y(),
-                    reference_vector.z()
+                    hit.distance(),
+                    hit.vector().x,
+                    hit.vector().y,
+                    hit.vector().z
                 );
             }
 
@@ -209,3 +1126,30 @@ impl GeographicArray {
         start_time.elapsed().as_micros()
     }
 }
+
+//picks the axis whose (min, max) index range covers the smallest share of its zones, it is the cheapest to walk
+//the axes can have different zone counts, so the share is compared rather than the number of zones
+//entries are (axis, start index, index range)
+//the axis the options ask for, or the most selective one for Auto
+fn select_axis<T>(config: &GeographicArrayConfig, options: &QueryOptions<'_, T>, ranges: [(Axis, usize, (usize, usize)); 3]) -> (Axis, usize) {
+    match options.axis_selection {
+        AxisSelection::Auto => most_selective_axis(config, ranges),
+        AxisSelection::Axis(axis) => ranges.iter()
+            .find(|(range_axis, _, _)| *range_axis == axis)
+            .map(|(range_axis, index, _)| (*range_axis, *index))
+            .unwrap_or((axis, 0)),
+    }
+}
+
+fn most_selective_axis(config: &GeographicArrayConfig, ranges: [(Axis, usize, (usize, usize)); 3]) -> (Axis, usize) {
+    let mut chosen: (Axis, usize) = (Axis::X, ranges[0].1);
+    let mut smallest_share: f64 = f64::INFINITY;
+    for (axis, index, range) in ranges {
+        let share_covered: f64 = AxisRange::new(config, &axis, Some(range)).len() as f64 / config.zones(&axis) as f64;
+        if share_covered < smallest_share {
+            smallest_share = share_covered;
+            chosen = (axis, index);
+        }
+    }
+    chosen
+}
@@ -0,0 +1,453 @@
+use crate::{GeographicArrayError, geodesy::{Geodetic, WGS84_SEMI_MAJOR_AXIS, WGS84_FLATTENING}};
+
+use std::{fmt, str::FromStr};
+
+//UTM is defined between these latitudes, the poles use UPS which isn't supported
+pub const UTM_MIN_LATITUDE: f64 = -80.0;
+pub const UTM_MAX_LATITUDE: f64 = 84.0;
+const SCALE_FACTOR: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500000.0;
+const FALSE_NORTHING_SOUTH: f64 = 10000000.0;
+//eastings are on the grid from the first 100km column to the end of the eighth, the MGRS column letters cover no more
+const MIN_EASTING: f64 = 100000.0;
+const MAX_EASTING: f64 = 900000.0;
+//84 north and 80 south with a margin for the parallels curving away from the central meridian
+const MAX_NORTHING_NORTH: f64 = 9400000.0;
+const MIN_NORTHING_SOUTH: f64 = 1100000.0;
+
+//MGRS letters, I and O are never used
+const BANDS: &[u8] = b"CDEFGHJKLMNPQRSTUVWX";
+const COLUMNS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+const ROWS: &[u8] = b"ABCDEFGHJKLMNPQRSTUV";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Hemisphere {
+    North,
+    South,
+}
+
+//a position on the UTM grid, zone 1 to 60 and meters east and north within it
+//the altitude isn't part of it, conversions to a Geodetic are given one
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Utm {
+    pub zone: u8,
+    pub hemisphere: Hemisphere,
+    pub easting: f64,
+    pub northing: f64,
+}
+
+impl Utm {
+    pub fn new(zone: u8, hemisphere: Hemisphere, easting: f64, northing: f64) -> Self {
+        Self::try_new(zone, hemisphere, easting, northing).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(zone: u8, hemisphere: Hemisphere, easting: f64, northing: f64) -> Result<Self, GeographicArrayError> {
+        if !(1..=60).contains(&zone) {
+            return Err(GeographicArrayError::InvalidGridReference(format!("UTM zone {} is not between 1 and 60", zone)));
+        }
+        if !(MIN_EASTING..MAX_EASTING).contains(&easting) {
+            return Err(GeographicArrayError::InvalidGridReference(format!("UTM easting {}", easting)));
+        }
+        let northings = match hemisphere {
+            Hemisphere::North => 0.0..=MAX_NORTHING_NORTH,
+            Hemisphere::South => MIN_NORTHING_SOUTH..=FALSE_NORTHING_SOUTH,
+        };
+        if !northings.contains(&northing) {
+            return Err(GeographicArrayError::InvalidGridReference(format!("UTM northing {}", northing)));
+        }
+        Ok(Self { zone, hemisphere, easting, northing })
+    }
+
+    //in the zone the point belongs to, including the wider zones around Norway and Svalbard
+    pub fn from_geodetic(geodetic: &Geodetic) -> Result<Self, GeographicArrayError> {
+        Self::from_geodetic_in_zone(geodetic, utm_zone(geodetic.latitude, geodetic.longitude))
+    }
+
+    //in any zone, useful for keeping points near a zone edge on the same grid as their neighbours
+    //accuracy falls off slowly away from the zone, it is still well under a millimeter a zone away
+    //errors once the easting is off the grid, about half a degree past the zone edge at the equator and further towards the poles
+    pub fn from_geodetic_in_zone(geodetic: &Geodetic, zone: u8) -> Result<Self, GeographicArrayError> {
+        geodetic.validate()?;
+        if !(UTM_MIN_LATITUDE..=UTM_MAX_LATITUDE).contains(&geodetic.latitude) {
+            return Err(GeographicArrayError::OutsideUtmCoverage { latitude: geodetic.latitude });
+        }
+        let hemisphere: Hemisphere = if geodetic.latitude < 0.0 { Hemisphere::South } else { Hemisphere::North };
+        let (easting, northing) = TransverseMercator::new().forward(geodetic.latitude, geodetic.longitude - central_meridian(zone));
+        Self::try_new(zone, hemisphere, easting + FALSE_EASTING, match hemisphere {
+            Hemisphere::North => northing,
+            Hemisphere::South => northing + FALSE_NORTHING_SOUTH,
+        })
+    }
+
+    pub fn to_geodetic(&self, altitude: f64) -> Geodetic {
+        let northing: f64 = match self.hemisphere {
+            Hemisphere::North => self.northing,
+            Hemisphere::South => self.northing - FALSE_NORTHING_SOUTH,
+        };
+        let (latitude, longitude) = TransverseMercator::new().reverse(self.easting - FALSE_EASTING, northing);
+        let mut longitude: f64 = longitude + central_meridian(self.zone);
+        if longitude > 180.0 {
+            longitude -= 360.0;
+        } else if longitude < -180.0 {
+            longitude += 360.0;
+        }
+        Geodetic { latitude, longitude, altitude }
+    }
+}
+
+//"31N 448251 5411932", the zone and hemisphere can also be separated, "31 N 448251 5411932"
+//N and S are always the hemisphere here, never an MGRS latitude band
+impl FromStr for Utm {
+    type Err = GeographicArrayError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || GeographicArrayError::InvalidGridReference(text.to_string());
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let (zone, hemisphere, easting, northing) = match tokens.as_slice() {
+            [zone_and_hemisphere, easting, northing] => {
+                let split: usize = zone_and_hemisphere.len().checked_sub(1).filter(|split| zone_and_hemisphere.is_char_boundary(*split)).ok_or_else(invalid)?;
+                (&zone_and_hemisphere[..split], &zone_and_hemisphere[split..], *easting, *northing)
+            },
+            [zone, hemisphere, easting, northing] => (*zone, *hemisphere, *easting, *northing),
+            _ => return Err(invalid()),
+        };
+        let hemisphere: Hemisphere = match hemisphere.to_ascii_uppercase().as_str() {
+            "N" => Hemisphere::North,
+            "S" => Hemisphere::South,
+            _ => return Err(invalid()),
+        };
+        Self::try_new(
+            zone.parse().map_err(|_| invalid())?,
+            hemisphere,
+            easting.parse().map_err(|_| invalid())?,
+            northing.parse().map_err(|_| invalid())?,
+        )
+    }
+}
+
+//to the meter, "31N 448251 5411932"
+impl fmt::Display for Utm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hemisphere: char = match self.hemisphere {
+            Hemisphere::North => 'N',
+            Hemisphere::South => 'S',
+        };
+        write!(f, "{}{} {:.0} {:.0}", self.zone, hemisphere, self.easting, self.northing)
+    }
+}
+
+//a Military Grid Reference System square, "18SUJ2338308451" is zone 18, band S, 100km square UJ, then easting and northing within it
+//precision is how many digits each of the easting and northing are written with, 5 is a meter square and 0 the whole 100km square
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mgrs {
+    pub zone: u8,
+    pub band: char,
+    pub column: char,
+    pub row: char,
+    pub easting: f64,   //meters from the west edge of the 100km square
+    pub northing: f64,  //meters from the south edge of the 100km square
+    pub precision: u8,
+}
+
+impl Mgrs {
+    pub fn from_geodetic(geodetic: &Geodetic, precision: u8) -> Result<Self, GeographicArrayError> {
+        if precision > 5 {
+            return Err(GeographicArrayError::InvalidGridReference(format!("MGRS precision {} is more than 5 digits", precision)));
+        }
+        let utm: Utm = Utm::from_geodetic(geodetic)?;
+        //the easting is 100km to 900km, so this is 0 to 7
+        let column: usize = (utm.easting / 100000.0).floor() as usize - 1;
+        let row: usize = (utm.northing / 100000.0).floor() as usize;
+        Ok(Self {
+            zone: utm.zone,
+            band: band(geodetic.latitude),
+            column: COLUMNS[column_set(utm.zone) * 8 + column] as char,
+            row: ROWS[(row + row_offset(utm.zone)) % ROWS.len()] as char,
+            easting: utm.easting.rem_euclid(100000.0),
+            northing: utm.northing.rem_euclid(100000.0),
+            precision,
+        })
+    }
+
+    pub fn to_utm(&self) -> Result<Utm, GeographicArrayError> {
+        let invalid = || GeographicArrayError::InvalidGridReference(self.to_string());
+        if !(1..=60).contains(&self.zone) {
+            return Err(invalid());
+        }
+        let band_index: usize = BANDS.iter().position(|band| *band as char == self.band).ok_or_else(invalid)?;
+        let column: usize = COLUMNS.iter().position(|column| *column as char == self.column)
+            .and_then(|column| column.checked_sub(column_set(self.zone) * 8))
+            .filter(|column| *column < 8)
+            .ok_or_else(invalid)?;
+        let row: usize = ROWS.iter().position(|row| *row as char == self.row).ok_or_else(invalid)?;
+        let hemisphere: Hemisphere = if self.band >= 'N' { Hemisphere::North } else { Hemisphere::South };
+
+        //the row letters repeat every 2000km, the band says which repeat it is
+        //the south of the band on the central meridian is the lowest northing it has, less a margin for the curve of the parallel across the zone
+        let band_south: f64 = UTM_MIN_LATITUDE + 8.0 * band_index as f64;
+        let lowest: f64 = Utm::from_geodetic_in_zone(&Geodetic { latitude: band_south, longitude: central_meridian(self.zone), altitude: 0.0 }, self.zone)?.northing - 100000.0;
+        let mut northing: f64 = ((row + ROWS.len() - row_offset(self.zone)) % ROWS.len()) as f64 * 100000.0 + self.northing;
+        while northing < lowest {
+            northing += 2000000.0;
+        }
+        Utm::try_new(self.zone, hemisphere, (column + 1) as f64 * 100000.0 + self.easting, northing)
+    }
+
+    pub fn to_geodetic(&self, altitude: f64) -> Result<Geodetic, GeographicArrayError> {
+        Ok(self.to_utm()?.to_geodetic(altitude))
+    }
+}
+
+//spaces are ignored, "18S UJ 23383 08451" is the same as "18SUJ2338308451"
+//the easting and northing are taken as the centre of the square they describe
+impl FromStr for Mgrs {
+    type Err = GeographicArrayError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || GeographicArrayError::InvalidGridReference(text.to_string());
+        let compact: String = text.split_whitespace().collect::<String>().to_ascii_uppercase();
+        let zone_digits: usize = compact.bytes().take_while(u8::is_ascii_digit).count();
+        if !(1..=2).contains(&zone_digits) {
+            return Err(invalid());
+        }
+        let zone: u8 = compact[..zone_digits].parse().map_err(|_| invalid())?;
+        let mut letters = compact[zone_digits..].chars();
+        let (band, column, row) = match (letters.next(), letters.next(), letters.next()) {
+            (Some(band), Some(column), Some(row)) => (band, column, row),
+            _ => return Err(invalid()),
+        };
+        let digits: &str = letters.as_str();
+        if !digits.len().is_multiple_of(2) || digits.len() > 10 || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let precision: usize = digits.len() / 2;
+        let size: f64 = 10f64.powi(5 - precision as i32);
+        let value = |digits: &str| -> Result<f64, GeographicArrayError> {
+            Ok(if digits.is_empty() { 0.0 } else { digits.parse::<f64>().map_err(|_| invalid())? } * size + size / 2.0)
+        };
+        let mgrs = Self {
+            zone,
+            band,
+            column,
+            row,
+            easting: value(&digits[..precision])?,
+            northing: value(&digits[precision..])?,
+            precision: precision as u8,
+        };
+        //checks the zone and that the letters belong to it
+        mgrs.to_utm()?;
+        Ok(mgrs)
+    }
+}
+
+//the easting and northing are truncated to the precision, as MGRS always is
+impl fmt::Display for Mgrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}{}", self.zone, self.band, self.column, self.row)?;
+        if self.precision > 0 {
+            let size: f64 = 10f64.powi(5 - self.precision as i32);
+            let width: usize = self.precision as usize;
+            write!(f, "{:0width$}{:0width$}", (self.easting / size).floor() as u32, (self.northing / size).floor() as u32, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+//zones are 6 degrees wide from 180 west, southwest Norway is in 32 and Svalbard is split between 31, 33, 35 and 37
+pub fn utm_zone(latitude: f64, longitude: f64) -> u8 {
+    if (56.0..64.0).contains(&latitude) && (3.0..12.0).contains(&longitude) {
+        return 32;
+    }
+    if (72.0..=84.0).contains(&latitude) && (0.0..42.0).contains(&longitude) {
+        return match longitude {
+            longitude if longitude < 9.0 => 31,
+            longitude if longitude < 21.0 => 33,
+            longitude if longitude < 33.0 => 35,
+            _ => 37,
+        };
+    }
+    ((((longitude + 180.0) / 6.0).floor() as i64).rem_euclid(60) + 1) as u8
+}
+
+pub fn central_meridian(zone: u8) -> f64 {
+    zone as f64 * 6.0 - 183.0
+}
+
+//8 degree bands from 80 south, X at the top is 12 degrees
+fn band(latitude: f64) -> char {
+    BANDS[(((latitude - UTM_MIN_LATITUDE) / 8.0).floor().max(0.0) as usize).min(BANDS.len() - 1)] as char
+}
+
+//the column letters run through three sets of 8, one set per zone
+fn column_set(zone: u8) -> usize {
+    (zone as usize - 1) % 3
+}
+
+//the row letters of even zones start 5 letters on
+fn row_offset(zone: u8) -> usize {
+    if zone.is_multiple_of(2) { 5 } else { 0 }
+}
+
+//the Krüger series to n^4 for the transverse mercator projection, see Karney, "Transverse Mercator with an accuracy of a few nanometers"
+//meters east and north of the central meridian on the equator, scaled by the UTM scale factor
+struct TransverseMercator {
+    eccentricity: f64,
+    rectifying_radius: f64,
+    alpha: [f64; 4],
+    beta: [f64; 4],
+}
+
+impl TransverseMercator {
+    fn new() -> Self {
+        let n: f64 = WGS84_FLATTENING / (2.0 - WGS84_FLATTENING);
+        let (n2, n3, n4) = (n * n, n * n * n, n * n * n * n);
+        Self {
+            eccentricity: (WGS84_FLATTENING * (2.0 - WGS84_FLATTENING)).sqrt(),
+            rectifying_radius: WGS84_SEMI_MAJOR_AXIS / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0),
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0,
+                49561.0 * n4 / 161280.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0,
+                4397.0 * n4 / 161280.0,
+            ],
+        }
+    }
+
+    //longitude is relative to the central meridian, both in degrees
+    fn forward(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let longitude: f64 = (longitude + 180.0).rem_euclid(360.0) - 180.0;
+        let (latitude, longitude) = (latitude.to_radians(), longitude.to_radians());
+        //the tangent of the conformal latitude
+        let tau: f64 = latitude.tan();
+        let sigma: f64 = (self.eccentricity * (self.eccentricity * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+        let conformal: f64 = tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt();
+        let xi: f64 = conformal.atan2(longitude.cos());
+        let eta: f64 = (longitude.sin() / (conformal * conformal + longitude.cos() * longitude.cos()).sqrt()).asinh();
+        let (mut easting, mut northing) = (eta, xi);
+        for (j, alpha) in self.alpha.iter().enumerate() {
+            let k: f64 = 2.0 * (j + 1) as f64;
+            easting += alpha * (k * xi).cos() * (k * eta).sinh();
+            northing += alpha * (k * xi).sin() * (k * eta).cosh();
+        }
+        (SCALE_FACTOR * self.rectifying_radius * easting, SCALE_FACTOR * self.rectifying_radius * northing)
+    }
+
+    //returns the latitude and the longitude relative to the central meridian, in degrees
+    fn reverse(&self, easting: f64, northing: f64) -> (f64, f64) {
+        let xi: f64 = northing / (SCALE_FACTOR * self.rectifying_radius);
+        let eta: f64 = easting / (SCALE_FACTOR * self.rectifying_radius);
+        let (mut xi_prime, mut eta_prime) = (xi, eta);
+        for (j, beta) in self.beta.iter().enumerate() {
+            let k: f64 = 2.0 * (j + 1) as f64;
+            xi_prime -= beta * (k * xi).sin() * (k * eta).cosh();
+            eta_prime -= beta * (k * xi).cos() * (k * eta).sinh();
+        }
+        let conformal: f64 = xi_prime.sin() / (eta_prime.sinh().powi(2) + xi_prime.cos().powi(2)).sqrt();
+        let longitude: f64 = eta_prime.sinh().atan2(xi_prime.cos());
+
+        //newton's method back from the conformal latitude, converges in two or three steps
+        let eccentricity_squared: f64 = self.eccentricity * self.eccentricity;
+        let mut tau: f64 = conformal;
+        for _ in 0..5 {
+            let sigma: f64 = (self.eccentricity * (self.eccentricity * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+            let estimate: f64 = tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt();
+            let step: f64 = (conformal - estimate) / (1.0 + estimate * estimate).sqrt()
+                * (1.0 + (1.0 - eccentricity_squared) * tau * tau) / ((1.0 - eccentricity_squared) * (1.0 + tau * tau).sqrt());
+            tau += step;
+            if step.abs() < 1e-14 * tau.abs().max(1.0) {
+                break;
+            }
+        }
+        (tau.atan().to_degrees(), longitude.to_degrees())
+    }
+}
+
+const GEOHASH_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+//12 characters is a cell a few centimeters across, past that an f64 runs out of bits to interleave
+pub const GEOHASH_MAX_LENGTH: usize = 12;
+
+//the cell a geohash names, latitude and longitude in degrees
+//the south and west edges are in the cell, the north and east edges belong to the next one, except at 90 north and 180 east
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GeohashCell {
+    pub south: f64,
+    pub north: f64,
+    pub west: f64,
+    pub east: f64,
+}
+
+impl GeohashCell {
+    //not case sensitive, anything from 1 to GEOHASH_MAX_LENGTH characters
+    pub fn decode(geohash: &str) -> Result<Self, GeographicArrayError> {
+        if geohash.is_empty() || geohash.len() > GEOHASH_MAX_LENGTH {
+            return Err(GeographicArrayError::InvalidGridReference(geohash.to_string()));
+        }
+        let mut cell = Self { south: -90.0, north: 90.0, west: -180.0, east: 180.0 };
+        //bits alternate between longitude and latitude, starting with longitude
+        let mut longitude_bit: bool = true;
+        for character in geohash.bytes() {
+            let value: usize = GEOHASH_ALPHABET.iter()
+                .position(|alphabet| *alphabet == character.to_ascii_lowercase())
+                .ok_or_else(|| GeographicArrayError::InvalidGridReference(geohash.to_string()))?;
+            for bit in (0..5).rev() {
+                let upper: bool = (value >> bit) & 1 == 1;
+                let (low, high) = if longitude_bit { (&mut cell.west, &mut cell.east) } else { (&mut cell.south, &mut cell.north) };
+                let middle: f64 = (*low + *high) / 2.0;
+                if upper {
+                    *low = middle;
+                } else {
+                    *high = middle;
+                }
+                longitude_bit = !longitude_bit;
+            }
+        }
+        Ok(cell)
+    }
+
+    pub fn centre(&self, altitude: f64) -> Geodetic {
+        Geodetic {
+            latitude: (self.south + self.north) / 2.0,
+            longitude: (self.west + self.east) / 2.0,
+            altitude,
+        }
+    }
+
+    pub fn contains(&self, geodetic: &Geodetic) -> bool {
+        geodetic.latitude >= self.south && (geodetic.latitude < self.north || self.north == 90.0)
+            && geodetic.longitude >= self.west && (geodetic.longitude < self.east || self.east == 180.0)
+    }
+}
+
+//length is how many characters, 1 to GEOHASH_MAX_LENGTH
+pub fn encode_geohash(geodetic: &Geodetic, length: usize) -> Result<String, GeographicArrayError> {
+    geodetic.validate()?;
+    if length == 0 || length > GEOHASH_MAX_LENGTH {
+        return Err(GeographicArrayError::InvalidGridReference(format!("a geohash of {} characters", length)));
+    }
+    let (mut south, mut north, mut west, mut east) = (-90.0, 90.0, -180.0, 180.0);
+    let mut longitude_bit: bool = true;
+    let mut geohash: String = String::with_capacity(length);
+    for _ in 0..length {
+        let mut value: usize = 0;
+        for _ in 0..5 {
+            let (low, high, coordinate) = if longitude_bit { (&mut west, &mut east, geodetic.longitude) } else { (&mut south, &mut north, geodetic.latitude) };
+            let middle: f64 = (*low + *high) / 2.0;
+            value <<= 1;
+            if coordinate >= middle {
+                value |= 1;
+                *low = middle;
+            } else {
+                *high = middle;
+            }
+            longitude_bit = !longitude_bit;
+        }
+        geohash.push(GEOHASH_ALPHABET[value] as char);
+    }
+    Ok(geohash)
+}
@@ -1,10 +1,16 @@
-use std::{ops::{Deref, Index}, rc::Rc, collections::BTreeMap};
+use std::{cell::Cell, rc::Rc, collections::{BinaryHeap, HashSet}, cmp::Ordering, fmt};
 
 use geographic_array::GeographicArray;
-use ordered_float::OrderedFloat;
+use metric::{DistanceMetric, Euclidean};
+use geodesy::{Geodetic, LocalFrame};
+use grid::{Utm, Mgrs, encode_geohash};
 use rand::{prelude::ThreadRng, Rng};
 
 pub mod geographic_array;
+pub mod metric;
+pub mod geodesy;
+pub mod grid;
+pub mod world;
 pub mod testing;
 
 pub const MAX_RADIUS_METERS_X: f64 = 65536.0;
@@ -12,16 +18,585 @@ pub const MAX_RADIUS_METERS_Y: f64 = 65536.0;
 pub const MAX_RADIUS_METERS_Z: f64 = 32768.0;
 
 pub const CUMULATIVE_DISTANCE_THRESHOLD: f64 = 10000.0; //within 10km cumulatively (x + y + z)
-pub const DISTANCE_THRESHOLD: f64 = 5000.0;
+pub const DISTANCE_THRESHOLD: f64 = 5000.0; //nearest searches stop here unless QueryOptions::max_distance says otherwise
 
 //Must be even, must be base 2
 pub const ZONES_USIZE: usize = 1048576; //Actual value to edit
 pub const ZONES_INDEXED_USIZE: usize = ZONES_USIZE - 1;
 pub const ZONES_F64: f64 = ZONES_USIZE as f64;
 
-type Candidates = BTreeMap<OrderedFloat<f64>, ReferenceVector>;
+//returned by the try_ constructors and queries instead of panicking on bad input
+#[derive(Clone, PartialEq, Debug)]
+pub enum GeographicArrayError {
+    OutOfBounds { axis: Axis, value: f64, limit: f64 },     //limit is the bound that was crossed
+    NonFinite { axis: Axis, value: f64 },
+    NormalisedOutOfRange { value: f64 },                    //expected 0 to 1
+    IndexOutOfRange { axis: Axis, index: usize, limit: usize },
+    InvalidRadius(f64),
+    UnknownEntry(EntryId),
+    InvalidBounds { axis: Axis, min: f64, max: f64 },      //min has to be below max
+    InvalidZoneCount { axis: Axis, zones: usize },
+    InvalidWeight { axis: Axis, weight: f64 },
+    InvalidGeodetic { latitude: f64, longitude: f64, altitude: f64 },
+    NoGeodeticOrigin,                                       //the array was made without GeographicArray::with_geodetic_origin
+    InvalidGridReference(String),                           //a UTM or MGRS reference that can't be read
+    OutsideUtmCoverage { latitude: f64 },                   //UTM and MGRS only cover 80 south to 84 north
+    InvalidTileSize { degrees: f64 },                       //see GeographicWorld::try_with_tile_degrees
+}
+
+impl fmt::Display for GeographicArrayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { axis, value, limit } => write!(f, "{:?} coordinate {} is outside the bound of {}", axis, value, limit),
+            Self::NonFinite { axis, value } => write!(f, "{:?} coordinate {} is not finite", axis, value),
+            Self::NormalisedOutOfRange { value } => write!(f, "normalised coordinate {} is outside 0 to 1", value),
+            Self::IndexOutOfRange { axis, index, limit } => write!(f, "{:?} zone index {} is greater than {}", axis, index, limit),
+            Self::InvalidRadius(radius) => write!(f, "radius {} must be finite and not negative", radius),
+            Self::UnknownEntry(id) => write!(f, "{:?} does not refer to a stored entry", id),
+            Self::InvalidBounds { axis, min, max } => write!(f, "{:?} bounds {} to {} are empty", axis, min, max),
+            Self::InvalidZoneCount { axis, zones } => write!(f, "{} zones on {:?} can't index anything, there has to be at least one", zones, axis),
+            Self::InvalidWeight { axis, weight } => write!(f, "{:?} weight {} must be finite and not negative", axis, weight),
+            Self::InvalidGeodetic { latitude, longitude, altitude } => write!(f, "latitude {} longitude {} altitude {} is not a position, latitude has to be within 90 and longitude within 180 degrees", latitude, longitude, altitude),
+            Self::NoGeodeticOrigin => write!(f, "the array has no geodetic origin to convert latitude and longitude against"),
+            Self::InvalidGridReference(reference) => write!(f, "{} is not a valid grid reference", reference),
+            Self::OutsideUtmCoverage { latitude } => write!(f, "latitude {} is outside the 80 south to 84 north UTM covers", latitude),
+            Self::InvalidTileSize { degrees } => write!(f, "tiles of {} degrees don't divide the world evenly or don't fit in the tile bounds", degrees),
+        }
+    }
+}
+
+impl std::error::Error for GeographicArrayError {}
+
+//what an insert does with a point outside the array's Bounds on any axis
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OutOfBoundsPolicy {
+    #[default]
+    Reject,     //returns GeographicArrayError::OutOfBounds
+    Clamp,      //stored in the edge zone of each axis it is outside of, its true coordinates are kept
+    Overflow,   //stored in a single bucket outside the zones, every query checks all of it
+}
+
+//the extent of the indexed space in meters, (min, max) on each axis
+//the two sides don't need to be the same distance from 0
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bounds {
+    x: (f64, f64),
+    y: (f64, f64),
+    z: (f64, f64),
+}
+
+impl Bounds {
+    pub fn new(x: (f64, f64), y: (f64, f64), z: (f64, f64)) -> Self {
+        Self::try_new(x, y, z).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(x: (f64, f64), y: (f64, f64), z: (f64, f64)) -> Result<Self, GeographicArrayError> {
+        for (axis, (min, max)) in [(Axis::X, x), (Axis::Y, y), (Axis::Z, z)] {
+            for value in [min, max] {
+                if !value.is_finite() {
+                    return Err(GeographicArrayError::NonFinite { axis, value });
+                }
+            }
+            if min >= max {
+                return Err(GeographicArrayError::InvalidBounds { axis, min, max });
+            }
+        }
+        Ok(Self { x, y, z })
+    }
+
+    //+-radius meters on each axis
+    pub fn symmetric(x_radius: f64, y_radius: f64, z_radius: f64) -> Self {
+        Self::new((-x_radius, x_radius), (-y_radius, y_radius), (-z_radius, z_radius))
+    }
+
+    //(min, max)
+    pub fn axis(&self, axis: &Axis) -> (f64, f64) {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+            Axis::Z => self.z,
+        }
+    }
+
+    pub fn span(&self, axis: &Axis) -> f64 {
+        let (min, max) = self.axis(axis);
+        max - min
+    }
+
+    pub fn contains(&self, vector: &Vector) -> bool {
+        self.validate(vector).is_ok()
+    }
+
+    pub fn clamp(&self, axis: &Axis, number: f64) -> f64 {
+        let (min, max) = self.axis(axis);
+        number.clamp(min, max)
+    }
+
+    pub fn validate(&self, vector: &Vector) -> Result<(), GeographicArrayError> {
+        self.validate_coordinate(&Axis::X, vector.x)?;
+        self.validate_coordinate(&Axis::Y, vector.y)?;
+        self.validate_coordinate(&Axis::Z, vector.z)
+    }
+
+    //checks a single coordinate is finite and within the bounds of its axis
+    pub fn validate_coordinate(&self, axis: &Axis, number: f64) -> Result<(), GeographicArrayError> {
+        let (min, max) = self.axis(axis);
+        if !number.is_finite() {
+            Err(GeographicArrayError::NonFinite { axis: *axis, value: number })
+        } else if number < min {
+            Err(GeographicArrayError::OutOfBounds { axis: *axis, value: number, limit: min })
+        } else if number > max {
+            Err(GeographicArrayError::OutOfBounds { axis: *axis, value: number, limit: max })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn normalise_zero_to_one(&self, axis: &Axis, number: f64) -> f64 {
+        let (min, max) = self.axis(axis);
+        (number - min) / (max - min)
+    }
+
+    pub fn normalise_negative_one_to_one(&self, axis: &Axis, number: f64) -> f64 {
+        2.0 * self.normalise_zero_to_one(axis, number) - 1.0
+    }
+}
+
+//+-MAX_RADIUS_METERS_*
+impl Default for Bounds {
+    fn default() -> Self {
+        Self::symmetric(MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z)
+    }
+}
+
+//everything that decides how coordinates map onto zones for one GeographicArray
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GeographicArrayConfig {
+    pub bounds: Bounds,
+    pub zones_x: usize,
+    pub zones_y: usize,
+    pub zones_z: usize,
+}
+
+impl GeographicArrayConfig {
+    //the same number of zones on every axis
+    pub fn new(bounds: Bounds, zones: usize) -> Self {
+        Self::with_zones(bounds, zones, zones, zones)
+    }
+
+    //sparse axes, usually Z, can be given fewer zones to save memory without losing resolution on the others
+    pub fn with_zones(bounds: Bounds, zones_x: usize, zones_y: usize, zones_z: usize) -> Self {
+        Self {
+            bounds,
+            zones_x,
+            zones_y,
+            zones_z,
+        }
+    }
+
+    pub fn zones(&self, axis: &Axis) -> usize {
+        match axis {
+            Axis::X => self.zones_x,
+            Axis::Y => self.zones_y,
+            Axis::Z => self.zones_z,
+        }
+    }
+
+    pub fn coordinate_to_index(&self, axis: &Axis, number: f64) -> usize {
+        self.try_coordinate_to_index(axis, number).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn validate(&self) -> Result<(), GeographicArrayError> {
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            if self.zones(&axis) == 0 {
+                return Err(GeographicArrayError::InvalidZoneCount { axis, zones: 0 });
+            }
+        }
+        Ok(())
+    }
+
+    //the index of the last zone on the axis
+    pub fn max_index(&self, axis: &Axis) -> usize {
+        self.zones(axis) - 1
+    }
+
+    pub fn try_coordinate_to_index(&self, axis: &Axis, number: f64) -> Result<usize, GeographicArrayError> {
+        self.bounds.validate_coordinate(axis, number)?;
+        self.try_normalised_coordinate_to_index(axis, self.bounds.normalise_zero_to_one(axis, number))
+    }
+
+    //implied 0 to 1 normalisation
+    pub fn try_normalised_coordinate_to_index(&self, axis: &Axis, number: f64) -> Result<usize, GeographicArrayError> {
+        //also rejects NaN, which would otherwise cast to index 0
+        if !(0.0..=1.0).contains(&number) {
+            return Err(GeographicArrayError::NormalisedOutOfRange { value: number });
+        }
+        Ok(((self.zones(axis) as f64 * number) - 1.0) as usize)
+    }
+
+    //coordinates outside the bounds are given the edge zone, anything in a further zone is also further from the coordinate
+    pub fn clamped_index(&self, axis: &Axis, number: f64) -> usize {
+        self.coordinate_to_index(axis, self.bounds.clamp(axis, number))
+    }
+
+    pub fn index_vector(&self, vector: &Vector) -> IndexVector {
+        IndexVector::new(
+            self.clamped_index(&Axis::X, vector.x),
+            self.clamped_index(&Axis::Y, vector.y),
+            self.clamped_index(&Axis::Z, vector.z),
+        )
+    }
+
+    //width of a single zone in meters on the given axis
+    pub fn zone_width(&self, axis: &Axis) -> f64 {
+        self.bounds.span(axis) / self.zones(axis) as f64
+    }
+
+    //returns the (min, max) zone indexes covered by the radius either side of the coordinate on the given axis
+    pub fn radius_to_index_range(&self, axis: &Axis, coordinate: &Vector, radius: f64) -> (usize, usize) {
+        let value: f64 = match axis {
+            Axis::X => coordinate.x,
+            Axis::Y => coordinate.y,
+            Axis::Z => coordinate.z,
+        };
+        self.bounds_to_index_range(axis, value - radius, value + radius)
+    }
+
+    //returns the (min, max) zone indexes covering lower to upper meters on the given axis, clamped to the edges of the structure
+    pub fn bounds_to_index_range(&self, axis: &Axis, lower: f64, upper: f64) -> (usize, usize) {
+        (self.clamped_index(axis, lower), self.clamped_index(axis, upper))
+    }
+}
+
+impl Default for GeographicArrayConfig {
+    fn default() -> Self {
+        Self::new(Bounds::default(), ZONES_USIZE)
+    }
+}
+
+//a point found during a search, before its value has been looked up
+//distance is measured from the coordinate that was searched from
+#[derive(Clone, PartialEq, Debug)]
+pub struct Candidate {
+    distance: f64,
+    id: EntryId,
+    vector: Vector,
+}
+
+impl Candidate {
+    pub(crate) fn new(distance: f64, reference_vector: &ReferenceVector) -> Self {
+        Self {
+            distance,
+            id: reference_vector.id(),
+            vector: Vector::from_reference_vector(reference_vector),
+        }
+    }
+
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    pub fn id(&self) -> EntryId {
+        self.id
+    }
+
+    pub fn vector(&self) -> &Vector {
+        &self.vector
+    }
+
+    //closest first, ties are broken by id so results are stable
+    fn cmp_by_distance(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+//collects hits from one or more searches, every entry is kept once however many axes found it
+//points at the same distance are all kept
+pub struct Candidates {
+    hits: Vec<Candidate>,
+    seen: HashSet<EntryId>,
+}
+
+impl Candidates {
+    pub fn new() -> Self {
+        Self {
+            hits: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    //returns false if this entry was already a candidate
+    pub fn insert(&mut self, hit: Candidate) -> bool {
+        if !self.seen.insert(hit.id) {
+            return false;
+        }
+        self.hits.push(hit);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.hits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty()
+    }
+
+    //keeps the closest len hits
+    pub fn truncate(&mut self, len: usize) {
+        if self.hits.len() > len {
+            self.hits.sort_unstable_by(Candidate::cmp_by_distance);
+            self.hits.truncate(len);
+        }
+    }
+
+    pub fn nearest_distance(&self) -> Option<f64> {
+        self.hits.iter().map(|hit| hit.distance).min_by(f64::total_cmp)
+    }
+
+    pub fn furthest_distance(&self) -> Option<f64> {
+        self.hits.iter().map(|hit| hit.distance).max_by(f64::total_cmp)
+    }
+
+    //every hit, closest first
+    pub fn into_sorted_vec(mut self) -> Vec<Candidate> {
+        self.hits.sort_unstable_by(Candidate::cmp_by_distance);
+        self.hits
+    }
+}
+
+impl Default for Candidates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//a single result of a search, with the value that was stored alongside the point
+#[derive(PartialEq, Debug)]
+pub struct QueryHit<'a, T> {
+    distance: f64,
+    id: EntryId,
+    vector: Vector,
+    local_frame: Option<&'a LocalFrame>,   //the array's geodetic origin, only used if the hit's position is asked for
+    value: &'a T,
+}
+
+impl<'a, T> QueryHit<'a, T> {
+    //None if the entry has been removed since it was found
+    fn from_candidate(geographic_array: &'a GeographicArray<T>, candidate: Candidate) -> Option<Self> {
+        Some(Self {
+            value: geographic_array.get_value(candidate.id)?,
+            distance: candidate.distance,
+            id: candidate.id,
+            local_frame: geographic_array.geodetic_origin(),
+            vector: candidate.vector,
+        })
+    }
+
+    //distance in meters from the coordinate that was searched from
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    pub fn id(&self) -> EntryId {
+        self.id
+    }
+
+    pub fn vector(&self) -> &Vector {
+        &self.vector
+    }
+
+    //latitude, longitude and altitude of the point, None unless the array has a geodetic origin
+    //worked out on each call, so queries don't pay for the conversion on hits nobody asks about
+    pub fn geodetic(&self) -> Option<Geodetic> {
+        self.local_frame.map(|local_frame| local_frame.to_geodetic(&self.vector))
+    }
+
+    //None without a geodetic origin or outside the latitudes UTM covers
+    pub fn utm(&self) -> Option<Utm> {
+        Utm::from_geodetic(&self.geodetic()?).ok()
+    }
+
+    pub fn mgrs(&self, precision: u8) -> Option<Mgrs> {
+        Mgrs::from_geodetic(&self.geodetic()?, precision).ok()
+    }
+
+    pub fn geohash(&self, length: usize) -> Option<String> {
+        encode_geohash(&self.geodetic()?, length).ok()
+    }
+
+    pub fn value(&self) -> &'a T {
+        self.value
+    }
+}
+
+impl<T> Clone for QueryHit<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            distance: self.distance,
+            id: self.id,
+            vector: self.vector.clone(),
+            local_frame: self.local_frame,
+            value: self.value,
+        }
+    }
+}
+
+//the results of a search, always ordered closest first
+#[derive(Clone, PartialEq, Debug)]
+pub struct QueryResult<'a, T> {
+    hits: Vec<QueryHit<'a, T>>,
+}
+
+impl<'a, T> QueryResult<'a, T> {
+    pub(crate) fn from_candidates(geographic_array: &'a GeographicArray<T>, candidates: Candidates) -> Self {
+        Self {
+            hits: candidates
+                .into_sorted_vec()
+                .into_iter()
+                .filter_map(|candidate| QueryHit::from_candidate(geographic_array, candidate))
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty()
+    }
+
+    //the closest hit
+    pub fn first(&self) -> Option<&QueryHit<'a, T>> {
+        self.hits.first()
+    }
+
+    //the furthest hit
+    pub fn last(&self) -> Option<&QueryHit<'a, T>> {
+        self.hits.last()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&QueryHit<'a, T>> {
+        self.hits.get(index)
+    }
+
+    //every hit no further than distance meters away
+    pub fn within(&self, distance: f64) -> &[QueryHit<'a, T>] {
+        let end: usize = self.hits.partition_point(|hit| hit.distance <= distance);
+        &self.hits[..end]
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, QueryHit<'a, T>> {
+        self.hits.iter()
+    }
+
+    pub fn as_slice(&self) -> &[QueryHit<'a, T>] {
+        &self.hits
+    }
+
+    pub fn into_vec(self) -> Vec<QueryHit<'a, T>> {
+        self.hits
+    }
+}
+
+impl<'a, T> IntoIterator for QueryResult<'a, T> {
+    type Item = QueryHit<'a, T>;
+    type IntoIter = std::vec::IntoIter<QueryHit<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hits.into_iter()
+    }
+}
+
+impl<'r, 'a, T> IntoIterator for &'r QueryResult<'a, T> {
+    type Item = &'r QueryHit<'a, T>;
+    type IntoIter = std::slice::Iter<'r, QueryHit<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hits.iter()
+    }
+}
+
+//settings any query can take, QueryOptions::new() gives the same results as the query without options
+//built up with the methods below, QueryOptions::new().max_distance(50.0).max_results(5)
+pub struct QueryOptions<'a, T> {
+    pub(crate) max_distance: Option<f64>,   //None keeps the query's own limit, DISTANCE_THRESHOLD for nearest searches, none for the rest
+    pub(crate) max_results: Option<usize>,  //the closest this many are kept
+    pub(crate) metric: Rc<dyn DistanceMetric>,
+    pub(crate) filter: BoxedFilter<'a, T>,
+    pub(crate) categories: Option<Vec<Category>>,
+    pub(crate) axis_selection: AxisSelection,
+}
+
+impl<'a, T> QueryOptions<'a, T> {
+    pub fn new() -> Self {
+        Self {
+            max_distance: None,
+            max_results: None,
+            metric: Rc::new(Euclidean),
+            filter: Box::new(|_, _| true),
+            categories: None,
+            axis_selection: AxisSelection::default(),
+        }
+    }
+
+    pub fn max_distance(mut self, max_distance: f64) -> Self {
+        self.max_distance = Some(max_distance);
+        self
+    }
+
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    pub fn metric<M: DistanceMetric + 'static>(mut self, metric: M) -> Self {
+        self.metric = Rc::new(metric);
+        self
+    }
+
+    pub fn filter<F: Fn(&Vector, &T) -> bool + 'a>(mut self, filter: F) -> Self {
+        self.filter = Box::new(filter);
+        self
+    }
+
+    pub fn categories(mut self, categories: &[Category]) -> Self {
+        self.categories = Some(categories.to_vec());
+        self
+    }
+
+    pub fn axis_selection(mut self, axis_selection: AxisSelection) -> Self {
+        self.axis_selection = axis_selection;
+        self
+    }
+
+    //Auto picks from these
+    pub(crate) fn axes(&self, auto: &[Axis]) -> Vec<Axis> {
+        match self.axis_selection {
+            AxisSelection::Auto => auto.to_vec(),
+            AxisSelection::Axis(axis) => vec![axis],
+        }
+    }
+}
 
-#[derive(Clone)]
+impl<T> Default for QueryOptions<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//which axis a query walks
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AxisSelection {
+    //what each query does without options, nearest walks X then Y then Z, k nearest and nearest_iter walk X,
+    //radius and box searches walk whichever covers the smallest share of its zones
+    #[default]
+    Auto,
+    Axis(Axis),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Axis {
     X,
     Y,
@@ -35,12 +610,28 @@ pub enum AxisIndex {
 }
 
 impl AxisIndex {
-    pub fn new(axis: &Axis, index: usize) -> Self {
-        assert!(index <= ZONES_INDEXED_USIZE);
-        match axis {
+    //config is that of the array the index is into
+    pub fn new(config: &GeographicArrayConfig, axis: &Axis, index: usize) -> Self {
+        Self::try_new(config, axis, index).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(config: &GeographicArrayConfig, axis: &Axis, index: usize) -> Result<Self, GeographicArrayError> {
+        let max_index: usize = config.max_index(axis);
+        if index > max_index {
+            return Err(GeographicArrayError::IndexOutOfRange { axis: *axis, index, limit: max_index });
+        }
+        Ok(match axis {
             Axis::X => Self::X(index),
             Axis::Y => Self::Y(index),
             Axis::Z => Self::Z(index),
+        })
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            Self::X(index) => *index,
+            Self::Y(index) => *index,
+            Self::Z(index) => *index,
         }
     }
 }
@@ -52,157 +643,280 @@ pub enum AxisRange {
 }
 
 impl AxisRange {
-    pub fn new(axis: &Axis, range: Option<(usize, usize)>) -> Self {
+    //config is that of the array the range is over, None covers every zone on the axis
+    pub fn new(config: &GeographicArrayConfig, axis: &Axis, range: Option<(usize, usize)>) -> Self {
+        Self::try_new(config, axis, range).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(config: &GeographicArrayConfig, axis: &Axis, range: Option<(usize, usize)>) -> Result<Self, GeographicArrayError> {
+        let max_index: usize = config.max_index(axis);
         let range_min;
         let range_max;
         if let Some((min, max)) = range {
-            assert!(min <= ZONES_INDEXED_USIZE);
-            assert!(max <= ZONES_INDEXED_USIZE);
-            range_min = match axis {
-                Axis::X => {
-                    match min {
-                        min if min > ZONES_INDEXED_USIZE => ZONES_INDEXED_USIZE,
-                        _ => min,
-                    }
-                },
-                Axis::Y => {
-                    match min {
-                        min if min > ZONES_INDEXED_USIZE => ZONES_INDEXED_USIZE,
-                        _ => min,
-                    }
-                },
-                Axis::Z => {
-                    match min {
-                        min if min > ZONES_INDEXED_USIZE => ZONES_INDEXED_USIZE,
-                        _ => min,
-                    }
-                },
-            };
-            range_max = match axis {
-                Axis::X => {
-                    match max {
-                        max if max > ZONES_INDEXED_USIZE => ZONES_INDEXED_USIZE,
-                        _ => max,
-                    }
-                },
-                Axis::Y => {
-                    match max {
-                        max if max > ZONES_INDEXED_USIZE => ZONES_INDEXED_USIZE,
-                        _ => max,
-                    }
-                },
-                Axis::Z => {
-                    match max {
-                        max if max > ZONES_INDEXED_USIZE => ZONES_INDEXED_USIZE,
-                        _ => max,
-                    }
-                },
-            };
+            for index in [min, max] {
+                if index > max_index {
+                    return Err(GeographicArrayError::IndexOutOfRange { axis: *axis, index, limit: max_index });
+                }
+            }
+            range_min = min;
+            range_max = max;
         } else {
             range_min = 0;
-            //these currently enter the same value, this will change when the number of zones is axis independant
-            range_max = match axis {
-                Axis::X => ZONES_INDEXED_USIZE,
-                Axis::Y => ZONES_INDEXED_USIZE,
-                Axis::Z => ZONES_INDEXED_USIZE,
-            };
+            range_max = max_index;
         }
         
-        match axis {
+        Ok(match axis {
             Axis::X => Self::X(range_min, range_max),
             Axis::Y => Self::Y(range_min, range_max),
             Axis::Z => Self::Z(range_min, range_max),
+        })
+    }
+
+    //inclusive on both ends
+    pub fn bounds(&self) -> (usize, usize) {
+        match self {
+            Self::X(min, max) => (*min, *max),
+            Self::Y(min, max) => (*min, *max),
+            Self::Z(min, max) => (*min, *max),
         }
     }
+
+    pub fn len(&self) -> usize {
+        let (min, max) = self.bounds();
+        if max < min {
+            0
+        } else {
+            max - min + 1
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub enum SearchMode {
     Nearest,
+    KNearest(usize),
     All,
     Radius(f64),
     Range(f64, f64),
+    Ground(Option<f64>),    //nearest by distance along the ground, optionally only within this many meters of altitude
 }
 
 pub struct DynamicSearchValidated {
+    config: GeographicArrayConfig,  //of the array being searched
     axis: Axis,
     coordinate: Vector,     //used for comparison only during work operation
     axis_index: AxisIndex,  //defines the work start position
     range: AxisRange,       //limits the work scope
     search_mode: SearchMode,
+    categories: Option<Vec<Category>>,  //None searches every category
+    metric: Rc<dyn DistanceMetric>,     //distances are ranked and limited by this, Euclidean unless with_metric is used
+    max_distance: Option<f64>,          //see max_distance()
 }
 
 impl DynamicSearchValidated {
-    pub fn new(axis: &Axis, nearest_to: &Vector, index: usize, search_mode: SearchMode) -> Self {
+    pub fn new(config: &GeographicArrayConfig, axis: &Axis, nearest_to: &Vector, index: usize, search_mode: SearchMode) -> Self {
         Self {
-            axis: axis.clone(),
+            config: *config,
+            axis: *axis,
             coordinate: nearest_to.clone(),                         //validated when the vector is created, Vector::{new(), generate_random(), generate_random_seeded()}
-            axis_index: AxisIndex::new(axis, index),                //validated in AxisIndex::new()
-            range: AxisRange::new(axis, match search_mode {   //validated in AxisRange::new()
-                SearchMode::Range(positive, negative) => match axis {
-                    Axis::X => Some((normalised_coordinate_to_index(normalise_zero_to_one_x(positive)), normalised_coordinate_to_index(normalise_zero_to_one_x(negative)))),
-                    Axis::Y => Some((normalised_coordinate_to_index(normalise_zero_to_one_y(positive)), normalised_coordinate_to_index(normalise_zero_to_one_y(negative)))),
-                    Axis::Z => Some((normalised_coordinate_to_index(normalise_zero_to_one_z(positive)), normalised_coordinate_to_index(normalise_zero_to_one_z(negative)))),
-                },
-                _ => None,
-            }),      
+            axis_index: AxisIndex::new(config, axis, index),                //validated in AxisIndex::new()
+            range: Self::range(config, axis, nearest_to, &search_mode, &Euclidean),
             search_mode,   
+            categories: None,
+            metric: Rc::new(Euclidean),
+            max_distance: None,
+        }
+    }
+
+    fn range(config: &GeographicArrayConfig, axis: &Axis, nearest_to: &Vector, search_mode: &SearchMode, metric: &dyn DistanceMetric) -> AxisRange {
+        AxisRange::new(config, axis, match search_mode {   //validated in AxisRange::new()
+            SearchMode::Range(positive, negative) => Some(config.bounds_to_index_range(axis, *negative, *positive)),
+            //the zones covered by the radius either side of the coordinate, clamped to the edges of the structure
+            //how far that reaches along the axis depends on the metric
+            SearchMode::Radius(radius) => Some(config.radius_to_index_range(axis, nearest_to, metric.axis_extent(axis, *radius))),
+            _ => None,
+        })
+    }
+
+    //ranks and limits by this metric instead of Euclidean distance, the radius range is widened or narrowed to suit
+    pub fn with_metric(mut self, metric: Rc<dyn DistanceMetric>) -> Self {
+        self.range = Self::range(&self.config, &self.axis, &self.coordinate, &self.search_mode, metric.as_ref());
+        self.metric = metric;
+        self
+    }
+
+    //nothing further than this is a candidate, None leaves the search mode's own limit
+    pub fn with_max_distance(mut self, max_distance: Option<f64>) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    //DISTANCE_THRESHOLD for nearest searches, which have to stop somewhere, nothing otherwise
+    fn max_distance(&self) -> f64 {
+        match (self.max_distance, &self.search_mode) {
+            (Some(max_distance), _) => max_distance,
+            (None, SearchMode::Nearest) | (None, SearchMode::Ground(_)) => DISTANCE_THRESHOLD,
+            (None, _) => f64::INFINITY,
         }
     }
 
+    //distance from the search coordinate by the search's metric
+    fn distance_to(&self, reference_vector: &ReferenceVector) -> f64 {
+        self.metric.distance(&Vector::from_reference_vector(reference_vector), &self.coordinate)
+    }
+
+    //restricts the search to these categories, groups of any other category in a zone are skipped without looking at their elements
+    pub fn with_categories(mut self, categories: Option<&[Category]>) -> Self {
+        self.categories = categories.map(|categories| categories.to_vec());
+        self
+    }
+
     //geographic_array is the structure that will be searched
     //candidates is a reference to the structure that good candidates will be stored in
     //further methods after the initial collection will be added that decide how searching will include or exclude items
     //order does matter
-    pub fn run(&self, geographic_array: &GeographicArray, candidates: &mut Candidates) {
+    //filter is run on every element before any distance validation, anything it rejects is never a candidate
+    pub fn run<T>(&self, geographic_array: &GeographicArray<T>, candidates: &mut Candidates, filter: &dyn Fn(&Vector, &T) -> bool) {
         fn remove(to_remove: &mut Vec<usize>, potential_candidates: &mut Vec<ReferenceVector>) {
             to_remove.reverse();
             for index in to_remove {
                 potential_candidates.remove(*index);
             }
         }
-        //it will be more efficient to sort through elements in a bag and exclude from there
-        //this might become a pre-processing function
-        fn invalidate_by_type(potential_candidates: &mut Vec<ReferenceVector>) {
+
+        //distance is measured along the ground by the search's metric, anything outside the altitude band is left as a potential candidate
+        fn validate_by_distance_as_the_crow_flies_along_the_ground(search: &DynamicSearchValidated, potential_candidates: &mut Vec<ReferenceVector>, candidates: &mut Candidates) {
             let mut to_remove: Vec<usize> = Vec::new();
-            for (i, _potential_candidate) in potential_candidates.iter_mut().enumerate() {
-                //Condition here
-                if false {
+            for (i, reference_vector) in potential_candidates.iter().enumerate() {
+                if !search.within_altitude_band(reference_vector) {
+                    continue;
+                }
+                let distance: f64 = search.distance_to(reference_vector);
+                if distance <= search.max_distance() {
+                    candidates.insert(Candidate::new(distance, reference_vector));
                     to_remove.push(i);
                 }
             }
             remove(&mut to_remove, potential_candidates);
         }
 
-        //not great at all, will replace entirely, but this is cheap, but more for invalidation than validation
-        fn validate_by_cumulative_distance(coordinate: &Vector, potential_candidates: &mut Vec<ReferenceVector>, candidates: &mut Candidates) {
+        //the distance is whatever the search's metric measures, as the crow flies unless another was given
+        fn validate_by_distance_threshold(search: &DynamicSearchValidated, potential_candidates: &mut Vec<ReferenceVector>, candidates: &mut Candidates) {
             let mut to_remove: Vec<usize> = Vec::new();
             for (i, reference_vector) in potential_candidates.iter().enumerate() {
-                let cumulative_diff: f64 = reference_vector.calculate_cumulative_diff(coordinate);
-                if cumulative_diff <= CUMULATIVE_DISTANCE_THRESHOLD {
-                    candidates.insert(OrderedFloat(cumulative_diff), reference_vector.to_real());
+                let distance: f64 = search.distance_to(reference_vector);
+                if distance <= search.max_distance() {
+                    candidates.insert(Candidate::new(distance, reference_vector));
                     to_remove.push(i);
                 }
             }
             remove(&mut to_remove, potential_candidates);
         }
 
-        fn _validate_by_distance_as_the_crow_flies_along_the_ground() {
-
-        }
-
-        fn validate_by_distance_as_the_crow_flies(coordinate: &Vector, potential_candidates: &mut Vec<ReferenceVector>, candidates: &mut Candidates) {
-            //calculate the direct distance between two vectors
+        fn validate_by_radius(search: &DynamicSearchValidated, radius: f64, potential_candidates: &mut Vec<ReferenceVector>, candidates: &mut Candidates) {
             let mut to_remove: Vec<usize> = Vec::new();
             for (i, reference_vector) in potential_candidates.iter().enumerate() {
-                let distance: f64 = distance_between(&Vector::from_reference_vector(reference_vector), coordinate);
-                if distance <= DISTANCE_THRESHOLD {
-                    candidates.insert(OrderedFloat(distance), reference_vector.to_real());
+                let distance: f64 = search.distance_to(reference_vector);
+                if distance <= radius {
+                    candidates.insert(Candidate::new(distance, reference_vector));
                     to_remove.push(i);
                 }
             }
             remove(&mut to_remove, potential_candidates);
         }
 
+        //a range search has no distance limit unless one was given, everything in the zones it covers is a candidate
+        if let SearchMode::Range(_, _) = self.search_mode {
+            for reference_vector in self.collect(geographic_array) {
+                let distance: f64 = self.distance_to(&reference_vector);
+                if distance <= self.max_distance() {
+                    candidates.insert(Candidate::new(distance, &reference_vector));
+                }
+            }
+            return;
+        }
+
+        //a radius search has to look at every zone the radius touches, stopping early would drop valid points
+        if let SearchMode::Radius(radius) = self.search_mode {
+            let radius: f64 = radius.min(self.max_distance());
+            if radius < 0.0 {
+                return;
+            }
+            let (range_min, range_max) = self.range.bounds();
+            for index in range_min..=range_max {
+                let mut potential_candidates: Vec<ReferenceVector> = geographic_array.zones(&self.axis)[index].iter_in(self.categories.as_deref()).cloned().collect();
+                invalidate_by_filter(geographic_array, filter, &mut potential_candidates);
+                validate_by_radius(self, radius, &mut potential_candidates, candidates);
+            }
+            let mut potential_candidates: Vec<ReferenceVector> = self.overflow(geographic_array);
+            invalidate_by_filter(geographic_array, filter, &mut potential_candidates);
+            validate_by_radius(self, radius, &mut potential_candidates, candidates);
+            return;
+        }
+
+        //keeps widening the ring until no unseen zone can hold anything closer than the k-th best found so far
+        if let SearchMode::KNearest(k) = self.search_mode {
+            if k == 0 {
+                return;
+            }
+            //the overflow bucket is taken whole before the rings, so it never affects the stopping rule
+            let mut potential_candidates: Vec<ReferenceVector> = self.overflow(geographic_array);
+            invalidate_by_filter(geographic_array, filter, &mut potential_candidates);
+            for reference_vector in potential_candidates {
+                let distance: f64 = self.distance_to(&reference_vector);
+                if distance <= self.max_distance() {
+                    candidates.insert(Candidate::new(distance, &reference_vector));
+                }
+            }
+            candidates.truncate(k);
+            let mut deviation_count: usize = 0;
+            while let Some(mut potential_candidates) = self.ring(geographic_array, deviation_count) {
+                invalidate_by_filter(geographic_array, filter, &mut potential_candidates);
+                for reference_vector in potential_candidates {
+                    let distance: f64 = self.distance_to(&reference_vector);
+                    if distance <= self.max_distance() {
+                        candidates.insert(Candidate::new(distance, &reference_vector));
+                    }
+                }
+                candidates.truncate(k);
+
+                //fewer than k may be within the max distance, nothing unseen can be once the rings pass it
+                if self.unseen_lower_bound(deviation_count) > self.max_distance() {
+                    break;
+                }
+                if candidates.len() == k {
+                    if let Some(furthest) = candidates.furthest_distance() {
+                        if furthest <= self.unseen_lower_bound(deviation_count) {
+                            break;
+                        }
+                    }
+                }
+                deviation_count += 1;
+            }
+            return;
+        }
+
+        //keeps widening the ring until nothing unseen could be closer along the ground than the nearest found, or the threshold is passed
+        //the overflow bucket is taken first, it is never part of a ring
+        if let SearchMode::Ground(_) = self.search_mode {
+            let mut potential_candidates: Vec<ReferenceVector> = self.overflow(geographic_array);
+            invalidate_by_filter(geographic_array, filter, &mut potential_candidates);
+            validate_by_distance_as_the_crow_flies_along_the_ground(self, &mut potential_candidates, candidates);
+            let mut deviation_count: usize = 0;
+            while let Some(mut potential_candidates) = self.ring(geographic_array, deviation_count) {
+                invalidate_by_filter(geographic_array, filter, &mut potential_candidates);
+                validate_by_distance_as_the_crow_flies_along_the_ground(self, &mut potential_candidates, candidates);
+                let unseen_lower_bound: f64 = self.unseen_lower_bound(deviation_count);
+                if unseen_lower_bound > self.max_distance() || candidates.nearest_distance().is_some_and(|nearest| nearest <= unseen_lower_bound) {
+                    break;
+                }
+                deviation_count += 1;
+            }
+            return;
+        }
 
         let mut can_move_positive_next_iteration: bool = true;
         let mut can_move_negative_next_iteration: bool = false;
@@ -210,28 +924,31 @@ impl DynamicSearchValidated {
         while candidates.is_empty() && (can_move_negative_next_iteration || can_move_positive_next_iteration) {
             let mut potential_candidates: Vec<ReferenceVector> = Vec::new();
             if can_move_positive_next_iteration {
-                potential_candidates.append(&mut match self.axis_index {
-                    AxisIndex::X(index) => geographic_array.x[index + deviation_count].clone(),
-                    AxisIndex::Y(index) => geographic_array.y[index + deviation_count].clone(),
-                    AxisIndex::Z(index) => geographic_array.z[index + deviation_count].clone(),
-                });
+                potential_candidates.extend(match self.axis_index {
+                    AxisIndex::X(index) => &geographic_array.x[index + deviation_count],
+                    AxisIndex::Y(index) => &geographic_array.y[index + deviation_count],
+                    AxisIndex::Z(index) => &geographic_array.z[index + deviation_count],
+                }.iter_in(self.categories.as_deref()).cloned());
             }
             if deviation_count > 0 && can_move_negative_next_iteration {
-                potential_candidates.append(&mut match self.axis_index {
-                    AxisIndex::X(index) => geographic_array.x[index - deviation_count].clone(),
-                    AxisIndex::Y(index) => geographic_array.y[index - deviation_count].clone(),
-                    AxisIndex::Z(index) => geographic_array.z[index - deviation_count].clone(),
-                });
+                potential_candidates.extend(match self.axis_index {
+                    AxisIndex::X(index) => &geographic_array.x[index - deviation_count],
+                    AxisIndex::Y(index) => &geographic_array.y[index - deviation_count],
+                    AxisIndex::Z(index) => &geographic_array.z[index - deviation_count],
+                }.iter_in(self.categories.as_deref()).cloned());
             }
             
-            //invalidates elements by a non existant condition, removing them from the potential candidates
+            //invalidates elements rejected by the filter, removing them from the potential candidates
             //this is a blacklisting function, not a whitelisting, blacklisting tasks should be run first
-            invalidate_by_type(&mut potential_candidates);
+            invalidate_by_filter(geographic_array, filter, &mut potential_candidates);
     
-            //invalidates elements by a constant currently defined in lib.rs
-            //validate_by_cumulative_distance(&self.coordinate, &mut potential_candidates, candidates);
-            validate_by_distance_as_the_crow_flies(&self.coordinate, &mut potential_candidates, candidates);
+            //invalidates elements further than the max distance, DISTANCE_THRESHOLD unless the query gave one
+            validate_by_distance_threshold(self, &mut potential_candidates, candidates);
             
+            //nothing in a zone not yet visited can be within the max distance
+            if self.unseen_lower_bound(deviation_count) > self.max_distance() {
+                break;
+            }
             deviation_count += 1;
             can_move_negative_next_iteration = (match self.axis_index {
                 AxisIndex::X(index) => index,
@@ -242,11 +959,193 @@ impl DynamicSearchValidated {
                 AxisIndex::X(index) => index,
                 AxisIndex::Y(index) => index,
                 AxisIndex::Z(index) => index,
-            } + deviation_count) <= ZONES_INDEXED_USIZE;
+            } + deviation_count) <= self.config.max_index(&self.axis);
+        }
+
+        //points in the overflow bucket within the threshold are kept if they are no further than what the zones found
+        let mut potential_candidates: Vec<ReferenceVector> = self.overflow(geographic_array);
+        invalidate_by_filter(geographic_array, filter, &mut potential_candidates);
+        let limit: f64 = candidates.furthest_distance().unwrap_or(self.max_distance());
+        for reference_vector in potential_candidates {
+            let distance: f64 = self.distance_to(&reference_vector);
+            if distance <= limit {
+                candidates.insert(Candidate::new(distance, &reference_vector));
+            }
+        }
+    }
+
+    //false only for ground searches given a band that the element is outside of
+    fn within_altitude_band(&self, reference_vector: &ReferenceVector) -> bool {
+        match self.search_mode {
+            SearchMode::Ground(Some(max_altitude_difference)) => (reference_vector.z() - self.coordinate.z).abs() <= max_altitude_difference,
+            _ => true,
+        }
+    }
+
+    //the elements of the overflow bucket in the searched categories
+    fn overflow<T>(&self, geographic_array: &GeographicArray<T>) -> Vec<ReferenceVector> {
+        geographic_array.overflow().iter_in(self.categories.as_deref()).cloned().collect()
+    }
+
+    //returns the elements in the two zones deviation_count either side of the start position
+    //None once both directions have run off the edge of the structure
+    fn ring<T>(&self, geographic_array: &GeographicArray<T>, deviation_count: usize) -> Option<Vec<ReferenceVector>> {
+        let index: usize = self.axis_index.index();
+        let can_move_positive: bool = index + deviation_count <= self.config.max_index(&self.axis);
+        let can_move_negative: bool = deviation_count > 0 && deviation_count <= index;
+        if !can_move_positive && !can_move_negative {
+            return None;
+        }
+        let zones = geographic_array.zones(&self.axis);
+        let mut potential_candidates: Vec<ReferenceVector> = Vec::new();
+        if can_move_positive {
+            potential_candidates.extend(zones[index + deviation_count].iter_in(self.categories.as_deref()).cloned());
+        }
+        if can_move_negative {
+            potential_candidates.extend(zones[index - deviation_count].iter_in(self.categories.as_deref()).cloned());
+        }
+        Some(potential_candidates)
+    }
+
+    //once the ring at deviation_count has been visited, anything in a zone that hasn't is more than deviation_count zones away on this axis
+    //one zone is given up as margin so a coordinate rounding onto a zone edge can't break the guarantee
+    //the metric turns that difference on the axis into the smallest distance it could mean
+    fn unseen_lower_bound(&self, deviation_count: usize) -> f64 {
+        self.metric.axis_lower_bound(&self.axis, deviation_count.saturating_sub(1) as f64 * self.config.zone_width(&self.axis))
+    }
+
+    //lazily walks outwards from the start position, see NearestIter
+    //the overflow bucket is pending from the start, it is never part of a ring
+    pub fn iter<'a, T>(self, geographic_array: &'a GeographicArray<T>, filter: BoxedFilter<'a, T>) -> NearestIter<'a, T> {
+        let mut overflow: Vec<ReferenceVector> = self.overflow(geographic_array);
+        invalidate_by_filter(geographic_array, filter.as_ref(), &mut overflow);
+        let pending: BinaryHeap<Pending> = overflow.iter().filter_map(|reference_vector| {
+            let distance: f64 = self.distance_to(reference_vector);
+            (distance <= self.max_distance()).then(|| Pending(Candidate::new(distance, reference_vector)))
+        }).collect();
+        NearestIter {
+            search: self,
+            geographic_array,
+            filter,
+            pending,
+            next_deviation_count: 0,
+            exhausted: false,
+            remaining: None,
+        }
+    }
+
+    //returns every element in the zones covered by the range and the overflow bucket, nothing is validated
+    //the caller decides what to keep, used for searches that aren't ordered by distance, like boxes
+    pub fn collect<T>(&self, geographic_array: &GeographicArray<T>) -> Vec<ReferenceVector> {
+        let mut collected: Vec<ReferenceVector> = self.overflow(geographic_array);
+        let (range_min, range_max) = self.range.bounds();
+        if range_max < range_min {
+            return collected;
+        }
+        for zone in &geographic_array.zones(&self.axis)[range_min..=range_max] {
+            collected.extend(zone.iter_in(self.categories.as_deref()).map(|reference_vector| reference_vector.to_real()));
+        }
+        collected
+    }
+}
+
+//a filter that outlives the call that created it, used by searches that run lazily
+pub type BoxedFilter<'a, T> = Box<dyn Fn(&Vector, &T) -> bool + 'a>;
+
+//this is a blacklisting function, elements the filter rejects are removed from the potential candidates
+//excluding by category is cheaper, that happens before this when the zones are read
+pub fn invalidate_by_filter<T>(geographic_array: &GeographicArray<T>, filter: &dyn Fn(&Vector, &T) -> bool, potential_candidates: &mut Vec<ReferenceVector>) {
+    potential_candidates.retain(|reference_vector| match geographic_array.value_of(reference_vector) {
+        Some(value) => filter(&Vector::from_reference_vector(reference_vector), value),
+        None => false,
+    });
+}
+
+//yields every element of a GeographicArray in increasing distance from the search coordinate
+//rings are only pulled in when the closest pending element can't be beaten by anything not yet visited
+pub struct NearestIter<'a, T> {
+    search: DynamicSearchValidated,
+    geographic_array: &'a GeographicArray<T>,
+    filter: BoxedFilter<'a, T>,
+    pending: BinaryHeap<Pending>,
+    next_deviation_count: usize,
+    exhausted: bool,
+    remaining: Option<usize>,   //None yields until the elements run out
+}
+
+impl<T> NearestIter<'_, T> {
+    //stops after this many have been yielded
+    pub(crate) fn with_max_results(mut self, max_results: Option<usize>) -> Self {
+        self.remaining = max_results;
+        self
+    }
+}
+
+impl<'a, T> Iterator for NearestIter<'a, T> {
+    type Item = QueryHit<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        loop {
+            //the last ring visited was next_deviation_count - 1
+            let unseen_lower_bound: f64 = self.search.unseen_lower_bound(self.next_deviation_count.saturating_sub(1));
+            //nothing beyond the max distance is ever pending, so once the rings pass it there's nothing left to visit
+            if self.next_deviation_count > 0 && unseen_lower_bound > self.search.max_distance() {
+                self.exhausted = true;
+            }
+            if let Some(closest) = self.pending.peek() {
+                if self.exhausted || closest.0.distance <= unseen_lower_bound {
+                    let Pending(candidate) = self.pending.pop()?;
+                    if let Some(remaining) = self.remaining.as_mut() {
+                        *remaining -= 1;
+                    }
+                    return QueryHit::from_candidate(self.geographic_array, candidate);
+                }
+            } else if self.exhausted {
+                return None;
+            }
+            match self.search.ring(self.geographic_array, self.next_deviation_count) {
+                Some(mut ring) => {
+                    invalidate_by_filter(self.geographic_array, self.filter.as_ref(), &mut ring);
+                    for reference_vector in ring {
+                        let distance: f64 = self.search.distance_to(&reference_vector);
+                        if distance <= self.search.max_distance() {
+                            self.pending.push(Pending(Candidate::new(distance, &reference_vector)));
+                        }
+                    }
+                    self.next_deviation_count += 1;
+                },
+                None => self.exhausted = true,
+            }
         }
     }
 }
 
+//reversed so the BinaryHeap pops the closest first
+struct Pending(Candidate);
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp_by_distance(&self.0)
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct IndexVector {
     pub x: usize,
@@ -263,14 +1162,29 @@ impl IndexVector {
         }
     }
 
-    pub fn from_vector(vector: &Vector) -> Self {
+    //panics if the vector is outside the config's bounds
+    pub fn from_vector(vector: &Vector, config: &GeographicArrayConfig) -> Self {
         Self {
-            x: normalised_coordinate_to_index(normalise_zero_to_one_x(vector.x)),
-            y: normalised_coordinate_to_index(normalise_zero_to_one_y(vector.y)),
-            z: normalised_coordinate_to_index(normalise_zero_to_one_z(vector.z)),
+            x: config.coordinate_to_index(&Axis::X, vector.x),
+            y: config.coordinate_to_index(&Axis::Y, vector.y),
+            z: config.coordinate_to_index(&Axis::Z, vector.z),
         }
     }
 
+    //coordinates outside the config's bounds are given the edge zone, see GeographicArrayConfig::index_vector
+    pub fn from_vector_clamped(vector: &Vector, config: &GeographicArrayConfig) -> Self {
+        config.index_vector(vector)
+    }
+
+    pub fn index(&self, axis: &Axis) -> usize {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+            Axis::Z => self.z,
+        }
+    }
+
+    #[allow(clippy::needless_late_init)]
     pub fn max_index(&self) -> usize {
         let maybe_largest: usize;
         if self.x > self.y {
@@ -295,13 +1209,23 @@ pub struct Vector {
 
 impl Vector {
     pub fn new(x: f64, y: f64, z: f64) -> Self {
-        assert!(x >= -MAX_RADIUS_METERS_X);
-        assert!(x <= MAX_RADIUS_METERS_X);
-        assert!(y >= -MAX_RADIUS_METERS_Y);
-        assert!(y <= MAX_RADIUS_METERS_Y);
-        assert!(z >= -MAX_RADIUS_METERS_Z);
-        assert!(z <= MAX_RADIUS_METERS_Z);
-        Self { x, y, z }
+        Self::try_new(x, y, z).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(x: f64, y: f64, z: f64) -> Result<Self, GeographicArrayError> {
+        let vector = Self { x, y, z };
+        vector.validate()?;
+        Ok(vector)
+    }
+
+    //only checks the coordinates are finite, whether they are in range is up to the array's Bounds and OutOfBoundsPolicy
+    pub fn validate(&self) -> Result<(), GeographicArrayError> {
+        for (axis, value) in [(Axis::X, self.x), (Axis::Y, self.y), (Axis::Z, self.z)] {
+            if !value.is_finite() {
+                return Err(GeographicArrayError::NonFinite { axis, value });
+            }
+        }
+        Ok(())
     }
 
     pub fn from_reference_vector(reference_vector: &ReferenceVector) -> Self {
@@ -330,29 +1254,121 @@ impl Vector {
     }
 }
 
+//small tag stored with each entry, zones keep their elements grouped by it so searches can skip whole groups
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Default)]
+pub struct Category(pub u16);
+
+//the elements of a single zone on one axis, grouped by category
+//there are rarely more than a handful of categories in a zone, so the groups are a plain Vec
+#[derive(Clone, Debug, Default)]
+pub struct Zone {
+    groups: Vec<(Category, Vec<ReferenceVector>)>,
+}
+
+impl Zone {
+    pub fn push(&mut self, category: Category, reference_vector: ReferenceVector) {
+        match self.groups.iter_mut().find(|(group_category, _)| *group_category == category) {
+            Some((_, group)) => group.push(reference_vector),
+            None => self.groups.push((category, vec![reference_vector])),
+        }
+    }
+
+    //empty groups are dropped so searches don't keep stepping over them
+    pub fn remove(&mut self, category: Category, id: EntryId) -> Option<ReferenceVector> {
+        let group_position: usize = self.groups.iter().position(|(group_category, _)| *group_category == category)?;
+        let group: &mut Vec<ReferenceVector> = &mut self.groups[group_position].1;
+        let position: usize = group.iter().position(|reference_vector| reference_vector.id() == id)?;
+        let reference_vector: ReferenceVector = group.swap_remove(position);
+        if group.is_empty() {
+            self.groups.swap_remove(group_position);
+        }
+        Some(reference_vector)
+    }
+
+    pub fn get_mut(&mut self, category: Category, id: EntryId) -> Option<&mut ReferenceVector> {
+        self.groups
+            .iter_mut()
+            .find(|(group_category, _)| *group_category == category)?
+            .1
+            .iter_mut()
+            .find(|reference_vector| reference_vector.id() == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ReferenceVector> {
+        self.groups.iter().flat_map(|(_, group)| group.iter())
+    }
+
+    //only the groups of the given categories, every group if None
+    pub fn iter_in<'a>(&'a self, categories: Option<&'a [Category]>) -> impl Iterator<Item = &'a ReferenceVector> {
+        self.groups
+            .iter()
+            .filter(move |(category, _)| categories.is_none_or(|categories| categories.contains(category)))
+            .flat_map(|(_, group)| group.iter())
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = Category> + '_ {
+        self.groups.iter().map(|(category, _)| *category)
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.iter().map(|(_, group)| group.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+//handle to a point stored in a GeographicArray, returned from insert and valid until the point is removed
+//slots are reused after a removal, the generation stops an old handle from finding whatever took its place
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct EntryId {
+    index: usize,
+    generation: u32,
+}
+
+impl EntryId {
+    pub(crate) fn new(index: usize, generation: u32) -> Self {
+        Self {
+            index,
+            generation,
+        }
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
+
+    pub(crate) fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+//id is the handle of the entry this point belongs to in its GeographicArray
 #[derive(Clone, PartialEq, Debug)]
 pub struct ReferenceVector {
     x: ValueType,
     y: ValueType,
     z: ValueType,
+    id: EntryId,
 }
 
 impl ReferenceVector {
-    pub fn generate_random() -> Self {
+    pub fn generate_random(id: EntryId) -> Self {
         let mut rng = rand::thread_rng();
         let x: f64 = rng.gen_range(-MAX_RADIUS_METERS_X..MAX_RADIUS_METERS_X);
         let y: f64 = rng.gen_range(-MAX_RADIUS_METERS_Y..MAX_RADIUS_METERS_Y);
         let z: f64 = rng.gen_range(-MAX_RADIUS_METERS_Z..MAX_RADIUS_METERS_Z);
 
-        Self::new(x, y, z)
+        Self::new(x, y, z, id)
     }
 
-    pub fn generate_random_seeded(rng: &mut ThreadRng) -> Self {
+    pub fn generate_random_seeded(rng: &mut ThreadRng, id: EntryId) -> Self {
         let x: f64 = rng.gen_range(-MAX_RADIUS_METERS_X..MAX_RADIUS_METERS_X);
         let y: f64 = rng.gen_range(-MAX_RADIUS_METERS_Y..MAX_RADIUS_METERS_Y);
         let z: f64 = rng.gen_range(-MAX_RADIUS_METERS_Z..MAX_RADIUS_METERS_Z);
 
-        Self::new(x, y, z)
+        Self::new(x, y, z, id)
     }
 
     pub fn to_real(&self) -> Self {
@@ -363,82 +1379,85 @@ impl ReferenceVector {
 
     pub fn make_real(&mut self) {
         if let ValueType::Reference(x) = self.x.clone() {
-            self.x = ValueType::Real(*x.deref());
+            self.x = ValueType::Real(x.get());
         }
         if let ValueType::Reference(y) = self.y.clone() {
-            self.y = ValueType::Real(*y.deref());
+            self.y = ValueType::Real(y.get());
         }
         if let ValueType::Reference(z) = self.z.clone() {
-            self.z = ValueType::Real(*z.deref());
+            self.z = ValueType::Real(z.get());
         }
     }
 
-    pub fn new_real_x(x: f64, y: Rc<f64>, z: Rc<f64>) -> Self {
+    pub fn new_real_x(x: f64, y: Rc<Cell<f64>>, z: Rc<Cell<f64>>, id: EntryId) -> Self {
         Self {
             x: ValueType::Real(x),
             y: ValueType::Reference(y),
             z: ValueType::Reference(z),
+            id,
         }
     }
 
-    pub fn new_real_y(x: Rc<f64>, y: f64, z: Rc<f64>) -> Self {
+    pub fn new_real_y(x: Rc<Cell<f64>>, y: f64, z: Rc<Cell<f64>>, id: EntryId) -> Self {
         Self {
             x: ValueType::Reference(x),
             y: ValueType::Real(y),
             z: ValueType::Reference(z),
+            id,
         }
     }
 
-    pub fn new_real_z(x: Rc<f64>, y: Rc<f64>, z: f64) -> Self {
+    pub fn new_real_z(x: Rc<Cell<f64>>, y: Rc<Cell<f64>>, z: f64, id: EntryId) -> Self {
         Self {
             x: ValueType::Reference(x),
             y: ValueType::Reference(y),
             z: ValueType::Real(z),
+            id,
         }
     }
 
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: f64, y: f64, z: f64, id: EntryId) -> Self {
         Self {
             x: ValueType::Real(x),
             y: ValueType::Real(y),
             z: ValueType::Real(z),
+            id,
         }
     }
 
-    pub fn x(&self) -> f64 {
-        self.x.get_value()
+    pub fn id(&self) -> EntryId {
+        self.id
     }
 
-    pub fn x_as_ref(&self) -> &f64 {
-        self.x.get_value_as_ref()
+    pub fn x(&self) -> f64 {
+        self.x.get_value()
     }
 
     pub fn y(&self) -> f64 {
         self.y.get_value()
     }
 
-    pub fn y_as_ref(&self) -> &f64 {
-        self.y.get_value_as_ref()
-    }
-
     pub fn z(&self) -> f64 {
         self.z.get_value()
     }
 
-    pub fn z_as_ref(&self) -> &f64 {
-        self.z.get_value_as_ref()
+    //writes the new coordinates into this element, shared coordinates are written through to every zone holding this point
+    pub fn set(&mut self, vector: &Vector) {
+        self.x.set_value(vector.x);
+        self.y.set_value(vector.y);
+        self.z.set_value(vector.z);
     }
 
     pub fn is_equal(&self, vector: &ReferenceVector) -> bool {
-        if self.x_as_ref() != vector.x_as_ref() {
+        if self.x() != vector.x() {
             return false;
         }
 
-        if self.y_as_ref() != vector.y_as_ref() {
+        if self.y() != vector.y() {
             return false;
         }
 
-        if self.z_as_ref() != vector.z_as_ref() {
+        if self.z() != vector.z() {
             return false;
         }
 
@@ -447,103 +1466,139 @@ impl ReferenceVector {
 
     pub fn calculate_cumulative_diff(&self, vector: &Vector) -> f64 {
         let mut temp: f64 = 0.0;
-        let self_x_ref = self.x_as_ref();
-        let self_y_ref = self.y_as_ref();
-        let self_z_ref = self.z_as_ref();
-        let vector_x_ref = &vector.x;
-        let vector_y_ref = &vector.y;
-        let vector_z_ref = &vector.z;
-        if self_x_ref > vector_x_ref {
-            temp += self_x_ref - vector_x_ref;
+        let self_x = self.x();
+        let self_y = self.y();
+        let self_z = self.z();
+        let vector_x = vector.x;
+        let vector_y = vector.y;
+        let vector_z = vector.z;
+        if self_x > vector_x {
+            temp += self_x - vector_x;
         } else {
-            temp += vector_x_ref - self_x_ref;
+            temp += vector_x - self_x;
         }
-        if self_y_ref > vector_y_ref {
-            temp += self_y_ref - vector_y_ref;
+        if self_y > vector_y {
+            temp += self_y - vector_y;
         } else {
-            temp += vector_y_ref - self_y_ref;
+            temp += vector_y - self_y;
         }
-        if self_z_ref > vector_z_ref {
-            temp += self_z_ref - vector_z_ref;
+        if self_z > vector_z {
+            temp += self_z - vector_z;
         } else {
-            temp += vector_z_ref - self_z_ref;
+            temp += vector_z - self_z;
         }
 
         temp
     }
 }
 
+//references are shared between the zones of the other two axes, a Cell lets a moved point be updated in place
 #[derive(Clone, PartialEq, Debug)]
 pub enum ValueType {
     Real(f64),
-    Reference(Rc<f64>),
+    Reference(Rc<Cell<f64>>),
 }
 
 impl ValueType {
     pub fn get_value(&self) -> f64 {
         match self {
             Self::Real(value) => *value,
-            Self::Reference(value) => *value.as_ref(),
+            Self::Reference(value) => value.get(),
         }
     }
 
-    pub fn get_value_as_ref(&self) -> &f64 {
+    pub fn set_value(&mut self, new_value: f64) {
         match self {
-            Self::Real(value) => value,
-            Self::Reference(value) => value.as_ref(),
+            Self::Real(value) => *value = new_value,
+            Self::Reference(value) => value.set(new_value),
         }
     }
 }
 
 
+//the free functions below are for GeographicArrayConfig::default() only, +-MAX_RADIUS_METERS_* split into ZONES_USIZE zones
+//an array with any other config gives different answers, use its Bounds and GeographicArrayConfig instead
 pub fn normalise_zero_to_one_x(number: f64) -> f64 {
-    (number - -MAX_RADIUS_METERS_X) / (MAX_RADIUS_METERS_X - -MAX_RADIUS_METERS_X)
+    Bounds::default().normalise_zero_to_one(&Axis::X, number)
 }
 
 pub fn normalise_zero_to_one_y(number: f64) -> f64 {
-    (number - -MAX_RADIUS_METERS_Y) / (MAX_RADIUS_METERS_Y - -MAX_RADIUS_METERS_Y)
+    Bounds::default().normalise_zero_to_one(&Axis::Y, number)
 }
 
 pub fn normalise_zero_to_one_z(number: f64) -> f64 {
-    (number - -MAX_RADIUS_METERS_Z) / (MAX_RADIUS_METERS_Z - -MAX_RADIUS_METERS_Z)
+    Bounds::default().normalise_zero_to_one(&Axis::Z, number)
 }
 
 
 pub fn normalise_negative_one_to_one_x(number: f64) -> f64 {
-    2.0 * ((number - -MAX_RADIUS_METERS_X) / (MAX_RADIUS_METERS_X - -MAX_RADIUS_METERS_X)) - 1.0
+    Bounds::default().normalise_negative_one_to_one(&Axis::X, number)
 }
 
 pub fn normalise_negative_one_to_one_y(number: f64) -> f64 {
-    2.0 * ((number - -MAX_RADIUS_METERS_Y) / (MAX_RADIUS_METERS_Y - -MAX_RADIUS_METERS_Y)) - 1.0
+    Bounds::default().normalise_negative_one_to_one(&Axis::Y, number)
 }
 
 pub fn normalise_negative_one_to_one_z(number: f64) -> f64 {
-    2.0 * ((number - -MAX_RADIUS_METERS_Z) / (MAX_RADIUS_METERS_Z - -MAX_RADIUS_METERS_Z)) - 1.0
+    Bounds::default().normalise_negative_one_to_one(&Axis::Z, number)
 }
 
 pub fn coordinate_to_index_x(number: f64) -> usize {
-    let index = normalised_coordinate_to_index(normalise_zero_to_one_x(number));
-    assert!(index <= ZONES_INDEXED_USIZE);
-    index
+    try_coordinate_to_index_x(number).unwrap_or_else(|error| panic!("{}", error))
 }
 
 pub fn coordinate_to_index_y(number: f64) -> usize {
-    let index = normalised_coordinate_to_index(normalise_zero_to_one_y(number));
-    assert!(index <= ZONES_INDEXED_USIZE);
-    index
+    try_coordinate_to_index_y(number).unwrap_or_else(|error| panic!("{}", error))
 }
 
 pub fn coordinate_to_index_z(number: f64) -> usize {
-    let index = normalised_coordinate_to_index(normalise_zero_to_one_z(number));
-    assert!(index <= ZONES_INDEXED_USIZE);
-    index
+    try_coordinate_to_index_z(number).unwrap_or_else(|error| panic!("{}", error))
+}
+
+pub fn try_coordinate_to_index_x(number: f64) -> Result<usize, GeographicArrayError> {
+    GeographicArrayConfig::default().try_coordinate_to_index(&Axis::X, number)
+}
+
+pub fn try_coordinate_to_index_y(number: f64) -> Result<usize, GeographicArrayError> {
+    GeographicArrayConfig::default().try_coordinate_to_index(&Axis::Y, number)
+}
+
+pub fn try_coordinate_to_index_z(number: f64) -> Result<usize, GeographicArrayError> {
+    GeographicArrayConfig::default().try_coordinate_to_index(&Axis::Z, number)
+}
+
+//checks a single coordinate is finite and within +-MAX_RADIUS_METERS_* for its axis
+pub fn validate_coordinate(axis: &Axis, number: f64) -> Result<(), GeographicArrayError> {
+    Bounds::default().validate_coordinate(axis, number)
+}
+
+//width of a single zone in meters on the given axis
+pub fn zone_width(axis: &Axis) -> f64 {
+    GeographicArrayConfig::default().zone_width(axis)
+}
+
+pub fn clamp_to_bounds(axis: &Axis, number: f64) -> f64 {
+    Bounds::default().clamp(axis, number)
+}
+
+//returns the (min, max) zone indexes covered by the radius either side of the coordinate on the given axis
+pub fn radius_to_index_range(axis: &Axis, coordinate: &Vector, radius: f64) -> (usize, usize) {
+    GeographicArrayConfig::default().radius_to_index_range(axis, coordinate, radius)
+}
+
+//returns the (min, max) zone indexes covering lower to upper meters on the given axis, clamped to the edges of the structure
+pub fn bounds_to_index_range(axis: &Axis, lower: f64, upper: f64) -> (usize, usize) {
+    GeographicArrayConfig::default().bounds_to_index_range(axis, lower, upper)
 }
 
 //implied 0 to 1 normalisation
 pub fn normalised_coordinate_to_index(number: f64) -> usize {
-    let index = ((ZONES_F64 * number) - 1.0) as usize;
-    assert!(index <= ZONES_INDEXED_USIZE);
-    index
+    try_normalised_coordinate_to_index(number).unwrap_or_else(|error| panic!("{}", error))
+}
+
+//ZONES_USIZE zones, whatever the axis
+pub fn try_normalised_coordinate_to_index(number: f64) -> Result<usize, GeographicArrayError> {
+    GeographicArrayConfig::default().try_normalised_coordinate_to_index(&Axis::X, number)
 }
 
 pub fn distance_between(one: &Vector, two: &Vector) -> f64 {
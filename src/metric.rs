@@ -0,0 +1,143 @@
+use crate::{Axis, Vector, GeographicArrayError};
+
+//how far apart two points are, searches rank and limit by this
+//searches only look at one axis at a time while deciding which zones to visit, so a metric also has to say
+//how close two points can be given only their difference on one axis
+pub trait DistanceMetric {
+    fn distance(&self, one: &Vector, two: &Vector) -> f64;
+
+    //the smallest distance possible between two points that are difference meters apart on the axis
+    //distance(one, two) must never be less than this, searches stop early based on it
+    fn axis_lower_bound(&self, axis: &Axis, difference: f64) -> f64;
+
+    //the furthest apart on the axis two points within distance of each other can be, the inverse of axis_lower_bound
+    fn axis_extent(&self, axis: &Axis, distance: f64) -> f64;
+}
+
+//as the crow flies, the default for every search
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Euclidean;
+
+impl DistanceMetric for Euclidean {
+    fn distance(&self, one: &Vector, two: &Vector) -> f64 {
+        crate::distance_between(one, two)
+    }
+
+    fn axis_lower_bound(&self, _axis: &Axis, difference: f64) -> f64 {
+        difference
+    }
+
+    fn axis_extent(&self, _axis: &Axis, distance: f64) -> f64 {
+        distance
+    }
+}
+
+//x + y + z, what ReferenceVector::calculate_cumulative_diff measures
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Manhattan;
+
+impl DistanceMetric for Manhattan {
+    fn distance(&self, one: &Vector, two: &Vector) -> f64 {
+        (two.x - one.x).abs() + (two.y - one.y).abs() + (two.z - one.z).abs()
+    }
+
+    fn axis_lower_bound(&self, _axis: &Axis, difference: f64) -> f64 {
+        difference
+    }
+
+    fn axis_extent(&self, _axis: &Axis, distance: f64) -> f64 {
+        distance
+    }
+}
+
+//the largest difference on any one axis
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Chebyshev;
+
+impl DistanceMetric for Chebyshev {
+    fn distance(&self, one: &Vector, two: &Vector) -> f64 {
+        (two.x - one.x).abs().max((two.y - one.y).abs()).max((two.z - one.z).abs())
+    }
+
+    fn axis_lower_bound(&self, _axis: &Axis, difference: f64) -> f64 {
+        difference
+    }
+
+    fn axis_extent(&self, _axis: &Axis, distance: f64) -> f64 {
+        distance
+    }
+}
+
+//as the crow flies along the ground, altitude is ignored
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Ground;
+
+impl DistanceMetric for Ground {
+    fn distance(&self, one: &Vector, two: &Vector) -> f64 {
+        ((two.x - one.x).powi(2) + (two.y - one.y).powi(2)).sqrt()
+    }
+
+    fn axis_lower_bound(&self, axis: &Axis, difference: f64) -> f64 {
+        match axis {
+            Axis::Z => 0.0,
+            _ => difference,
+        }
+    }
+
+    fn axis_extent(&self, axis: &Axis, distance: f64) -> f64 {
+        match axis {
+            Axis::Z => f64::INFINITY,
+            _ => distance,
+        }
+    }
+}
+
+//euclidean with each axis scaled first, Weighted::new(1.0, 1.0, 3.0) makes a meter of altitude count as three
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Weighted {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl Weighted {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self::try_new(x, y, z).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    //weights have to be finite and not negative, a weight of 0 ignores the axis
+    pub fn try_new(x: f64, y: f64, z: f64) -> Result<Self, GeographicArrayError> {
+        for (axis, weight) in [(Axis::X, x), (Axis::Y, y), (Axis::Z, z)] {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(GeographicArrayError::InvalidWeight { axis, weight });
+            }
+        }
+        Ok(Self { x, y, z })
+    }
+
+    pub fn weight(&self, axis: &Axis) -> f64 {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+            Axis::Z => self.z,
+        }
+    }
+}
+
+impl DistanceMetric for Weighted {
+    fn distance(&self, one: &Vector, two: &Vector) -> f64 {
+        ((self.x * (two.x - one.x)).powi(2) + (self.y * (two.y - one.y)).powi(2) + (self.z * (two.z - one.z)).powi(2)).sqrt()
+    }
+
+    fn axis_lower_bound(&self, axis: &Axis, difference: f64) -> f64 {
+        self.weight(axis) * difference
+    }
+
+    //an axis with no weight can be any distance apart
+    fn axis_extent(&self, axis: &Axis, distance: f64) -> f64 {
+        match self.weight(axis) {
+            weight if weight > 0.0 => distance / weight,
+            _ => f64::INFINITY,
+        }
+    }
+}
@@ -2,10 +2,45 @@
 mod tests {
     use crate::{
         geographic_array::GeographicArray,
+        metric::{DistanceMetric, Euclidean, Manhattan, Chebyshev, Weighted, Ground},
+        geodesy::{Geodetic, Ecef, LocalFrame, geodetic_to_ecef, ecef_to_geodetic},
+        grid::{Utm, Mgrs, Hemisphere, utm_zone, GeohashCell, encode_geohash},
+        world::{GeographicWorld, WorldEntryId, WORLD_TILE_DEGREES},
         normalised_coordinate_to_index,
     };
 
-    use crate::{Vector, MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z, normalise_negative_one_to_one_x, normalise_negative_one_to_one_y, normalise_negative_one_to_one_z, normalise_zero_to_one_x, normalise_zero_to_one_y, normalise_zero_to_one_z, IndexVector, Axis, distance_between};
+    use rand::{Rng, rngs::ThreadRng};
+
+    use crate::{Vector, MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z, normalise_negative_one_to_one_x, normalise_negative_one_to_one_y, normalise_negative_one_to_one_z, normalise_zero_to_one_x, normalise_zero_to_one_y, normalise_zero_to_one_z, IndexVector, EntryId, Category, Axis, distance_between, coordinate_to_index_x, coordinate_to_index_y, coordinate_to_index_z, GeographicArrayError, AxisIndex, try_coordinate_to_index_z, try_normalised_coordinate_to_index, OutOfBoundsPolicy, Bounds, GeographicArrayConfig, ZONES_USIZE, AxisRange, QueryOptions, QueryResult, AxisSelection};
+
+    //small enough to build in every test, the searches are exact whatever the zone count
+    fn small_config() -> GeographicArrayConfig {
+        GeographicArrayConfig::with_zones(Bounds::default(), 4096, 4096, 512)
+    }
+
+    //each point's value is its insert order
+    fn insert_random(geographic_array: &mut GeographicArray<usize>, count: usize, rng: &mut ThreadRng) -> Vec<Vector> {
+        let mut inserted: Vec<Vector> = Vec::new();
+        for _ in 0..count {
+            let value = Vector::generate_random_seeded(rng);
+            geographic_array.insert(value.clone(), geographic_array.len());
+            inserted.push(value);
+        }
+        inserted
+    }
+
+    //k nearest, the first k off the iterator and the radius count, against a brute force pass over every inserted point
+    fn assert_exact<T, M: DistanceMetric + Clone + 'static>(geographic_array: &GeographicArray<T>, inserted: &[Vector], centre: &Vector, metric: M, k: usize, radius: f64) {
+        let mut expected: Vec<f64> = inserted.iter().map(|value| metric.distance(value, centre)).collect();
+        expected.sort_by(f64::total_cmp);
+        let nearest: &[f64] = &expected[..k.min(expected.len())];
+        let found: Vec<f64> = geographic_array.find_k_nearest_with_metric(centre, k, metric.clone()).iter().map(|hit| hit.distance()).collect();
+        assert_eq!(found, nearest);
+        let iterated: Vec<f64> = geographic_array.nearest_iter_with_metric(centre, metric.clone()).take(k).map(|hit| hit.distance()).collect();
+        assert_eq!(iterated, nearest);
+        let within = geographic_array.find_within_radius_with_metric(centre, radius, metric);
+        assert_eq!(within.len(), expected.iter().filter(|distance| **distance <= radius).count());
+    }
 
     #[test]
     fn test_normalise_negative_one_to_one() {
@@ -64,6 +99,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(unused_variables)]
     fn test_index() {
         let mut test_values: Vec<(f64, f64, f64, Axis, usize)> = Vec::new();
         //Just X for now
@@ -139,31 +175,1095 @@ mod tests {
         let mut rng = rand::thread_rng();
         for _ in 0..1 {
             let mut geographic_array = GeographicArray::default();
-            let mut synthetic_values: Vec<(Vector, Option<IndexVector>)> = vec![(Vector::generate_random_seeded(&mut rng), None); 100];
+            let mut synthetic_values: Vec<(Vector, Option<EntryId>)> = vec![(Vector::generate_random_seeded(&mut rng), None); 100];
             for (i, (value, _)) in synthetic_values.clone().iter().enumerate() {
-                synthetic_values[i].1 = Some(geographic_array.insert(value.clone()));
+                synthetic_values[i].1 = Some(geographic_array.insert(value.clone(), ()));
             }
             for _ in 0..1000000 {
-                geographic_array.insert(Vector::generate_random_seeded(&mut rng));
+                geographic_array.insert(Vector::generate_random_seeded(&mut rng), ());
             }
-            for (value, index) in synthetic_values.iter() {
+            for (value, _index) in synthetic_values.iter() {
                 let near_candidates = geographic_array.find_nearest(value);
                 assert!(!near_candidates.is_empty());
                 let mut first: bool = true;
-                for (cumulative_distance, coordinate) in near_candidates {
+                for hit in near_candidates {
                     if first {
-                        assert_eq!(cumulative_distance, 0.0);
+                        assert_eq!(hit.distance(), 0.0);
                         first = false;
                     }
                     println!(
                         "Distance: {:17}, X: {}, Y: {}, Z: {}",
-                        cumulative_distance,
-                        coordinate.x(),
-                        coordinate.y(),
-                        coordinate.z()
+                        hit.distance(),
+                        hit.vector().x,
+                        hit.vector().y,
+                        hit.vector().z
                     );
                 }
             }
         }
     }
+
+    #[test]
+    fn test_find_within_radius() {
+        let mut rng = rand::thread_rng();
+        let mut geographic_array = GeographicArray::default();
+        let mut inserted: Vec<Vector> = Vec::new();
+        for _ in 0..100000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            geographic_array.insert(value.clone(), ());
+            inserted.push(value);
+        }
+        for radius in [0.0, 2500.0, 10000.0, 40000.0] {
+            for _ in 0..5 {
+                let centre = Vector::generate_random_seeded(&mut rng);
+                let expected: usize = inserted.iter().filter(|value| distance_between(value, &centre) <= radius).count();
+                let found = geographic_array.find_within_radius(&centre, radius);
+                assert_eq!(found.len(), expected);
+                for hit in found {
+                    assert!(hit.distance() <= radius);
+                    assert_eq!(hit.distance(), distance_between(hit.vector(), &centre));
+                }
+            }
+        }
+
+        //a point sitting exactly on the centre is always found
+        let centre = inserted[0].clone();
+        let found = geographic_array.find_within_radius(&centre, 0.0);
+        assert!(!found.is_empty());
+    }
+
+    #[test]
+    fn test_find_in_box() {
+        let mut rng = rand::thread_rng();
+        let mut geographic_array = GeographicArray::default();
+        let mut inserted: Vec<Vector> = Vec::new();
+        for _ in 0..100000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            geographic_array.insert(value.clone(), ());
+            inserted.push(value);
+        }
+        for _ in 0..10 {
+            let one = Vector::generate_random_seeded(&mut rng);
+            let two = Vector::generate_random_seeded(&mut rng);
+            let min = Vector::new(one.x.min(two.x), one.y.min(two.y), one.z.min(two.z));
+            let max = Vector::new(one.x.max(two.x), one.y.max(two.y), one.z.max(two.z));
+            let expected: usize = inserted.iter().filter(|value| {
+                value.x >= min.x && value.x <= max.x
+                    && value.y >= min.y && value.y <= max.y
+                    && value.z >= min.z && value.z <= max.z
+            }).count();
+            assert_eq!(geographic_array.find_in_box(&min, &max).len(), expected);
+        }
+
+        //a box with no volume still finds the point sitting on it
+        let found = geographic_array.find_in_box(&inserted[0], &inserted[0]);
+        assert!(found.iter().any(|hit| *hit.vector() == inserted[0]));
+    }
+
+    #[test]
+    fn test_find_k_nearest() {
+        let mut rng = rand::thread_rng();
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::with_config(small_config());
+        //sparse on purpose, most nearest neighbours will be well past DISTANCE_THRESHOLD
+        let inserted: Vec<Vector> = insert_random(&mut geographic_array, 200, &mut rng);
+        for k in [1, 5, 50, 500] {
+            for _ in 0..5 {
+                let nearest_to = Vector::generate_random_seeded(&mut rng);
+                assert_exact(&geographic_array, &inserted, &nearest_to, Euclidean, k, 20000.0);
+                assert_eq!(geographic_array.find_k_nearest(&nearest_to, k).len(), k.min(200));
+            }
+        }
+        assert!(geographic_array.find_k_nearest(&inserted[0], 0).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_iter() {
+        let mut rng = rand::thread_rng();
+        let mut geographic_array = GeographicArray::default();
+        let mut inserted: Vec<Vector> = Vec::new();
+        for _ in 0..1000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            geographic_array.insert(value.clone(), ());
+            inserted.push(value);
+        }
+        let nearest_to = Vector::generate_random_seeded(&mut rng);
+        let mut expected: Vec<f64> = inserted.iter().map(|value| distance_between(value, &nearest_to)).collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        //taking a few only visits what it needs, draining it gives everything back in order
+        let first_few: Vec<f64> = geographic_array.nearest_iter(&nearest_to).take(10).map(|hit| hit.distance()).collect();
+        assert_eq!(first_few, expected[..10]);
+        let everything: Vec<f64> = geographic_array.nearest_iter(&nearest_to).map(|hit| hit.distance()).collect();
+        assert_eq!(everything, expected);
+    }
+
+    #[test]
+    fn test_values_stored_alongside_points() {
+        let mut rng = rand::thread_rng();
+        let mut geographic_array: GeographicArray<String> = GeographicArray::default();
+        let mut inserted: Vec<Vector> = Vec::new();
+        for i in 0..1000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            geographic_array.insert(value.clone(), format!("entity {}", i));
+            inserted.push(value);
+        }
+        assert_eq!(geographic_array.len(), 1000);
+        for (i, value) in inserted.iter().enumerate() {
+            let hit = geographic_array.find_k_nearest(value, 1).into_iter().next().unwrap();
+            assert_eq!(hit.distance(), 0.0);
+            assert_eq!(geographic_array.get_value(hit.id()), Some(&format!("entity {}", i)));
+        }
+    }
+
+    #[test]
+    fn test_entry_ids() {
+        let mut rng = rand::thread_rng();
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::default();
+        let mut inserted: Vec<(EntryId, Vector)> = Vec::new();
+        for i in 0..1000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            inserted.push((geographic_array.insert(value.clone(), i), value));
+        }
+        for (i, (id, value)) in inserted.iter().enumerate() {
+            assert!(geographic_array.contains(*id));
+            assert_eq!(geographic_array.get(*id), Some(value));
+            assert_eq!(geographic_array.get_value(*id), Some(&i));
+            assert_eq!(geographic_array.get_index_vector(*id), Some(&IndexVector::from_vector(value, geographic_array.config())));
+            let hit = geographic_array.find_k_nearest(value, 1).into_iter().next().unwrap();
+            assert_eq!(hit.id(), *id);
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut rng = rand::thread_rng();
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::default();
+        let mut inserted: Vec<(EntryId, Vector)> = Vec::new();
+        for i in 0..2000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            inserted.push((geographic_array.insert(value.clone(), i), value));
+        }
+        let (kept, removed) = inserted.split_at(1000);
+        for (i, (id, value)) in removed.iter().enumerate() {
+            let removed_entry = if i % 2 == 0 {
+                geographic_array.remove(*id)
+            } else {
+                geographic_array.remove_exact(value)
+            };
+            assert_eq!(removed_entry, Some((*id, value.clone(), i + 1000)));
+            assert!(!geographic_array.contains(*id));
+            assert_eq!(geographic_array.remove(*id), None);
+        }
+        assert_eq!(geographic_array.len(), 1000);
+
+        //nothing removed can be found on any axis
+        let total: usize = [&geographic_array.x, &geographic_array.y, &geographic_array.z].iter().map(|zones| zones.iter().map(|zone| zone.len()).sum::<usize>()).sum();
+        assert_eq!(total, 3000);
+        let everything: Vec<EntryId> = geographic_array.nearest_iter(&Vector::new(0.0, 0.0, 0.0)).map(|hit| hit.id()).collect();
+        assert_eq!(everything.len(), 1000);
+        for (id, _) in kept {
+            assert!(everything.contains(id));
+        }
+
+        //a reused slot doesn't answer to the handle of the entry that was there before
+        let reused = geographic_array.insert(Vector::generate_random_seeded(&mut rng), 2000);
+        assert!(removed.iter().all(|(id, _)| *id != reused && !geographic_array.contains(*id)));
+        assert_eq!(geographic_array.get_value(reused), Some(&2000));
+    }
+
+    #[test]
+    fn test_update_position() {
+        let mut rng = rand::thread_rng();
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::default();
+        let mut inserted: Vec<(EntryId, Vector)> = Vec::new();
+        for i in 0..1000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            inserted.push((geographic_array.insert(value.clone(), i), value));
+        }
+        for (i, (id, value)) in inserted.iter_mut().enumerate() {
+            //half move a long way, the rest nudge within their zones
+            let moved = if i % 2 == 0 {
+                Vector::generate_random_seeded(&mut rng)
+            } else {
+                Vector::new(value.x - 0.01 * value.x.signum(), value.y - 0.01 * value.y.signum(), value.z)
+            };
+            assert!(geographic_array.update_position(*id, moved.clone()));
+            assert_eq!(geographic_array.get(*id), Some(&moved));
+            assert_eq!(geographic_array.get_index_vector(*id), Some(&IndexVector::from_vector(&moved, geographic_array.config())));
+            *value = moved;
+        }
+        for (i, (id, value)) in inserted.iter().enumerate() {
+            //every axis has to agree on the new coordinates, whichever zone is searched from
+            for (zones, index) in [(&geographic_array.x, coordinate_to_index_x(value.x)), (&geographic_array.y, coordinate_to_index_y(value.y)), (&geographic_array.z, coordinate_to_index_z(value.z))] {
+                let reference_vector = zones[index].iter().find(|reference_vector| reference_vector.id() == *id).unwrap();
+                assert_eq!(Vector::from_reference_vector(reference_vector), *value);
+            }
+            let hit = geographic_array.find_k_nearest(value, 1).into_iter().next().unwrap();
+            assert_eq!(hit.distance(), 0.0);
+            assert_eq!(geographic_array.get_value(hit.id()), Some(&i));
+        }
+        let total: usize = [&geographic_array.x, &geographic_array.y, &geographic_array.z].iter().map(|zones| zones.iter().map(|zone| zone.len()).sum::<usize>()).sum();
+        assert_eq!(total, 3000);
+
+        let (id, _) = inserted[0].clone();
+        geographic_array.remove(id);
+        assert!(!geographic_array.update_position(id, Vector::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_equal_distances_are_all_kept() {
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::default();
+        //the same coordinate several times over, and a symmetric ring around the origin
+        let mut ids: Vec<EntryId> = Vec::new();
+        for i in 0..5 {
+            ids.push(geographic_array.insert(Vector::new(1000.0, 1000.0, 0.0), i));
+        }
+        for (x, y, z) in [(100.0, 0.0, 0.0), (-100.0, 0.0, 0.0), (0.0, 100.0, 0.0), (0.0, -100.0, 0.0), (0.0, 0.0, 100.0), (0.0, 0.0, -100.0)] {
+            ids.push(geographic_array.insert(Vector::new(x, y, z), ids.len()));
+        }
+        let origin = Vector::new(0.0, 0.0, 0.0);
+
+        //the first zone on X holds the four points sitting on x = 0, all 100m away
+        let found = geographic_array.find_nearest(&origin);
+        assert_eq!(found.len(), 4);
+        assert!(found.iter().all(|hit| hit.distance() == 100.0));
+        let mut found_ids: Vec<EntryId> = found.iter().map(|hit| hit.id()).collect();
+        found_ids.dedup();
+        assert_eq!(found_ids.len(), 4);
+
+        assert_eq!(geographic_array.find_within_radius(&origin, 100.0).len(), 6);
+        assert_eq!(geographic_array.find_k_nearest(&origin, 8).len(), 8);
+        assert_eq!(geographic_array.find_k_nearest(&Vector::new(1000.0, 1000.0, 0.0), 5).iter().filter(|hit| hit.distance() == 0.0).count(), 5);
+        assert_eq!(geographic_array.nearest_iter(&origin).count(), 11);
+    }
+
+    #[test]
+    fn test_query_result() {
+        let mut geographic_array: GeographicArray<&str> = GeographicArray::default();
+        let depot = geographic_array.insert(Vector::new(10.0, 0.0, 0.0), "depot");
+        geographic_array.insert(Vector::new(0.0, 20.0, 0.0), "hydrant");
+        geographic_array.insert(Vector::new(0.0, 0.0, 30.0), "camera");
+        geographic_array.insert(Vector::new(-40.0, 0.0, 0.0), "camera");
+
+        let found = geographic_array.find_k_nearest(&Vector::new(0.0, 0.0, 0.0), 10);
+        assert_eq!(found.len(), 4);
+        let first = found.first().unwrap();
+        assert_eq!(first.id(), depot);
+        assert_eq!(first.distance(), 10.0);
+        assert_eq!(first.vector(), &Vector::new(10.0, 0.0, 0.0));
+        assert_eq!(*first.value(), "depot");
+        assert_eq!(found.last().unwrap().distance(), 40.0);
+
+        let within: Vec<&str> = found.within(25.0).iter().map(|hit| *hit.value()).collect();
+        assert_eq!(within, vec!["depot", "hydrant"]);
+        assert!(found.within(5.0).is_empty());
+        assert_eq!(found.within(40.0).len(), 4);
+
+        let mut by_reference: Vec<f64> = Vec::new();
+        for hit in &found {
+            by_reference.push(hit.distance());
+        }
+        let by_value: Vec<f64> = found.into_iter().map(|hit| hit.distance()).collect();
+        assert_eq!(by_reference, vec![10.0, 20.0, 30.0, 40.0]);
+        assert_eq!(by_reference, by_value);
+    }
+
+    #[test]
+    fn test_filtered_queries() {
+        let mut rng = rand::thread_rng();
+        //(ambulance number, busy)
+        let mut geographic_array: GeographicArray<(usize, bool)> = GeographicArray::default();
+        let busy = Vector::new(0.0, 0.0, 0.0);
+        let mut inserted: Vec<(Vector, (usize, bool))> = vec![(busy.clone(), (0, true)), (Vector::new(0.0, 10.0, 0.0), (1, true)), (Vector::new(3000.0, 0.0, 0.0), (2, false))];
+        for (value, ambulance) in &inserted {
+            geographic_array.insert(value.clone(), *ambulance);
+        }
+        let available = |_: &Vector, ambulance: &(usize, bool)| !ambulance.1;
+
+        //the zone holding the query point only has busy ambulances, the search has to carry on past it
+        let nearest = geographic_array.find_nearest_filtered(&busy, available);
+        assert_eq!(nearest.len(), 1);
+        assert_eq!(nearest.first().unwrap().value().0, 2);
+        assert_eq!(geographic_array.find_k_nearest_filtered(&busy, 1, available).first().unwrap().value().0, 2);
+        assert_eq!(geographic_array.nearest_iter_filtered(&busy, available).next().unwrap().value().0, 2);
+        assert!(geographic_array.find_within_radius_filtered(&busy, 100.0, available).is_empty());
+
+        for i in 3..10000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            let ambulance = (i, i % 3 == 0);
+            geographic_array.insert(value.clone(), ambulance);
+            inserted.push((value, ambulance));
+        }
+        let centre = Vector::generate_random_seeded(&mut rng);
+        let expected: usize = inserted.iter().filter(|(value, ambulance)| !ambulance.1 && distance_between(value, &centre) <= 20000.0).count();
+        let found = geographic_array.find_within_radius_filtered(&centre, 20000.0, available);
+        assert_eq!(found.len(), expected);
+        assert!(found.iter().all(|hit| !hit.value().1));
+        let min = Vector::new((centre.x - 10000.0).max(-MAX_RADIUS_METERS_X), (centre.y - 10000.0).max(-MAX_RADIUS_METERS_Y), -MAX_RADIUS_METERS_Z);
+        let max = Vector::new((centre.x + 10000.0).min(MAX_RADIUS_METERS_X), (centre.y + 10000.0).min(MAX_RADIUS_METERS_Y), MAX_RADIUS_METERS_Z);
+        assert!(geographic_array.find_in_box_filtered(&min, &max, available).iter().all(|hit| !hit.value().1));
+        assert!(geographic_array.find_k_nearest_filtered(&centre, 50, available).iter().all(|hit| !hit.value().1));
+    }
+
+    #[test]
+    fn test_categories() {
+        let mut rng = rand::thread_rng();
+        let hydrant = Category(0);
+        let camera = Category(1);
+        let depot = Category(2);
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::default();
+        let origin = Vector::new(0.0, 0.0, 0.0);
+        let closest_hydrant = geographic_array.insert_with_category(origin.clone(), 0, hydrant);
+        let closest_depot = geographic_array.insert_with_category(Vector::new(0.0, 50.0, 0.0), 1, depot);
+        assert_eq!(geographic_array.get_category(closest_hydrant), Some(hydrant));
+        let mut inserted: Vec<(Vector, Category)> = vec![(origin.clone(), hydrant), (Vector::new(0.0, 50.0, 0.0), depot)];
+
+        //the zone holding the origin has a group for each category
+        let mut categories: Vec<Category> = geographic_array.z[coordinate_to_index_z(0.0)].categories().collect();
+        categories.sort();
+        assert_eq!(categories, vec![hydrant, depot]);
+
+        for i in 2..10000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            let category = Category((i % 3) as u16);
+            geographic_array.insert_with_category(value.clone(), i, category);
+            inserted.push((value, category));
+        }
+        let wanted = [camera, depot];
+        assert_eq!(geographic_array.find_nearest_in_categories(&origin, &wanted).first().unwrap().id(), closest_depot);
+        assert_eq!(geographic_array.find_k_nearest_in_categories(&origin, 1, &wanted).first().unwrap().id(), closest_depot);
+        assert_eq!(geographic_array.nearest_iter_in_categories(&origin, &[hydrant]).next().unwrap().id(), closest_hydrant);
+
+        let centre = Vector::generate_random_seeded(&mut rng);
+        let expected: usize = inserted.iter().filter(|(value, category)| wanted.contains(category) && distance_between(value, &centre) <= 20000.0).count();
+        let found = geographic_array.find_within_radius_in_categories(&centre, 20000.0, &wanted);
+        assert_eq!(found.len(), expected);
+        assert!(found.iter().all(|hit| wanted.contains(&geographic_array.get_category(hit.id()).unwrap())));
+        let everything_in_box = geographic_array.find_in_box_in_categories(&Vector::new(-MAX_RADIUS_METERS_X, -MAX_RADIUS_METERS_Y, -MAX_RADIUS_METERS_Z), &Vector::new(MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z), &[camera]);
+        assert_eq!(everything_in_box.len(), inserted.iter().filter(|(_, category)| *category == camera).count());
+
+        //moving and removing keep the point in its category's group
+        assert!(geographic_array.update_position(closest_depot, Vector::new(20000.0, 0.0, 0.0)));
+        assert_eq!(geographic_array.find_k_nearest_in_categories(&Vector::new(20000.0, 0.0, 0.0), 1, &[depot]).first().unwrap().id(), closest_depot);
+        geographic_array.remove(closest_hydrant);
+        assert!(geographic_array.z[coordinate_to_index_z(0.0)].iter().all(|reference_vector| reference_vector.id() != closest_hydrant));
+    }
+
+    #[test]
+    fn test_errors_instead_of_panics() {
+        //a vector is only checked for being finite, the array it goes into checks its own bounds
+        assert!(Vector::try_new(MAX_RADIUS_METERS_X + 1.0, 0.0, 0.0).is_ok());
+        assert_eq!(
+            Bounds::default().validate(&Vector::new(MAX_RADIUS_METERS_X + 1.0, 0.0, 0.0)),
+            Err(GeographicArrayError::OutOfBounds { axis: Axis::X, value: MAX_RADIUS_METERS_X + 1.0, limit: MAX_RADIUS_METERS_X })
+        );
+        assert_eq!(
+            Bounds::default().validate(&Vector::new(0.0, 0.0, -MAX_RADIUS_METERS_Z - 1.0)),
+            Err(GeographicArrayError::OutOfBounds { axis: Axis::Z, value: -MAX_RADIUS_METERS_Z - 1.0, limit: -MAX_RADIUS_METERS_Z })
+        );
+        assert!(matches!(Vector::try_new(0.0, f64::NAN, 0.0), Err(GeographicArrayError::NonFinite { axis: Axis::Y, .. })));
+        assert_eq!(Vector::try_new(1.0, 2.0, 3.0), Ok(Vector::new(1.0, 2.0, 3.0)));
+
+        assert!(matches!(AxisIndex::try_new(&GeographicArrayConfig::default(), &Axis::X, usize::MAX), Err(GeographicArrayError::IndexOutOfRange { axis: Axis::X, .. })));
+        assert!(matches!(try_coordinate_to_index_z(f64::INFINITY), Err(GeographicArrayError::NonFinite { axis: Axis::Z, .. })));
+        assert_eq!(try_coordinate_to_index_z(0.0), Ok(coordinate_to_index_z(0.0)));
+        assert!(try_normalised_coordinate_to_index(f64::NAN).is_err());
+        assert!(try_normalised_coordinate_to_index(1.5).is_err());
+
+        //the fields are public, so a bad fix can still reach the array without going through try_new
+        let mut geographic_array: GeographicArray<&str> = GeographicArray::default();
+        let bad_fix = Vector { x: f64::NAN, y: 0.0, z: 0.0 };
+        assert!(geographic_array.try_insert(bad_fix.clone(), "bad").is_err());
+        assert!(geographic_array.is_empty());
+        let id = geographic_array.try_insert(Vector::new(10.0, 10.0, 10.0), "good").unwrap();
+        assert!(matches!(geographic_array.try_update_position(id, bad_fix.clone()), Err(GeographicArrayError::NonFinite { axis: Axis::X, .. })));
+        assert_eq!(geographic_array.get(id), Some(&Vector::new(10.0, 10.0, 10.0)));
+        geographic_array.remove(id);
+        assert_eq!(geographic_array.try_update_position(id, Vector::new(0.0, 0.0, 0.0)), Err(GeographicArrayError::UnknownEntry(id)));
+
+        assert!(geographic_array.try_find_nearest(&bad_fix).is_err());
+        assert!(geographic_array.try_find_k_nearest(&bad_fix, 1).is_err());
+        assert!(geographic_array.try_nearest_iter(&bad_fix).is_err());
+        assert!(geographic_array.try_find_in_box(&bad_fix, &Vector::new(0.0, 0.0, 0.0)).is_err());
+        assert_eq!(geographic_array.try_find_within_radius(&Vector::new(0.0, 0.0, 0.0), -1.0).err(), Some(GeographicArrayError::InvalidRadius(-1.0)));
+        assert!(geographic_array.try_find_within_radius(&Vector::new(0.0, 0.0, 0.0), 100.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_out_of_bounds_policy() {
+        let past_edge = Vector::new(MAX_RADIUS_METERS_X + 100.0, 0.0, 0.0);
+        let edge = Vector::new(MAX_RADIUS_METERS_X, 0.0, 0.0);
+        assert!(Vector::try_new(f64::NAN, 0.0, 0.0).is_err());
+
+        let mut rejecting: GeographicArray<()> = GeographicArray::with_config(small_config());
+        assert_eq!(rejecting.out_of_bounds_policy(), OutOfBoundsPolicy::Reject);
+        assert!(matches!(rejecting.try_insert(past_edge.clone(), ()), Err(GeographicArrayError::OutOfBounds { axis: Axis::X, .. })));
+        assert!(rejecting.is_empty());
+
+        //clamped points sit in the edge zone but keep their real coordinates
+        let mut clamping: GeographicArray<()> = GeographicArray::with_config(small_config()).with_out_of_bounds_policy(OutOfBoundsPolicy::Clamp);
+        let clamped = clamping.insert(past_edge.clone(), ());
+        assert_eq!(clamping.get(clamped), Some(&past_edge));
+        assert_eq!(clamping.get_index_vector(clamped).unwrap().x, clamping.config().coordinate_to_index(&Axis::X, MAX_RADIUS_METERS_X));
+        assert_eq!(clamping.find_k_nearest(&edge, 1).first().unwrap().distance(), 100.0);
+        assert_eq!(clamping.find_within_radius(&edge, 150.0).len(), 1);
+        assert!(clamping.find_within_radius(&edge, 50.0).is_empty());
+        assert_eq!(clamping.remove_exact(&past_edge).map(|(id, _, _)| id), Some(clamped));
+
+        let mut rng = rand::thread_rng();
+        let mut overflowing: GeographicArray<usize> = GeographicArray::with_config(small_config()).with_out_of_bounds_policy(OutOfBoundsPolicy::Overflow);
+        let mut inserted: Vec<Vector> = insert_random(&mut overflowing, 2000, &mut rng);
+        let overflowed = overflowing.insert(past_edge.clone(), 2000);
+        let below = Vector::new(0.0, 0.0, -MAX_RADIUS_METERS_Z - 10.0);
+        overflowing.insert(below.clone(), 2001);
+        inserted.push(past_edge.clone());
+        inserted.push(below.clone());
+        assert_eq!(overflowing.overflow().len(), 2);
+        assert_eq!(overflowing.get_index_vector(overflowed), None);
+        assert_eq!(overflowing.len(), 2002);
+
+        //every query looks in the overflow bucket, even when searching from outside the bounds
+        assert!(overflowing.find_nearest(&edge).iter().any(|hit| hit.id() == overflowed));
+        assert_eq!(overflowing.find_k_nearest(&past_edge, 1).first().unwrap().id(), overflowed);
+        assert_eq!(overflowing.nearest_iter(&past_edge).next().unwrap().id(), overflowed);
+        assert_eq!(*overflowing.find_within_radius(&below, 1.0).first().unwrap().value(), 2001);
+        let everything = overflowing.find_in_box(
+            &Vector::new(-MAX_RADIUS_METERS_X, -MAX_RADIUS_METERS_Y, -2.0 * MAX_RADIUS_METERS_Z),
+            &Vector::new(2.0 * MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z),
+        );
+        assert_eq!(everything.len(), 2002);
+        assert_exact(&overflowing, &inserted, &Vector::generate_random_seeded(&mut rng), Euclidean, 20, 10000.0);
+        assert_exact(&overflowing, &inserted, &past_edge, Euclidean, 20, 10000.0);
+
+        //moving in and out of the overflow bucket
+        assert!(overflowing.update_position(overflowed, edge.clone()));
+        assert_eq!(overflowing.overflow().len(), 1);
+        assert!(overflowing.get_index_vector(overflowed).is_some());
+        assert_eq!(overflowing.find_k_nearest(&edge, 1).first().unwrap().id(), overflowed);
+        assert!(overflowing.update_position(overflowed, past_edge.clone()));
+        assert_eq!(overflowing.overflow().len(), 2);
+        assert!(overflowing.z.iter().all(|zone| zone.iter().all(|reference_vector| reference_vector.id() != overflowed)));
+        assert_eq!(overflowing.remove_exact(&below).map(|(_, _, value)| value), Some(2001));
+        assert_eq!(overflowing.overflow().len(), 1);
+    }
+
+    #[test]
+    fn test_per_instance_bounds() {
+        assert_eq!(Bounds::try_new((10.0, 10.0), (0.0, 1.0), (0.0, 1.0)), Err(GeographicArrayError::InvalidBounds { axis: Axis::X, min: 10.0, max: 10.0 }));
+        assert!(matches!(Bounds::try_new((0.0, 1.0), (0.0, f64::INFINITY), (0.0, 1.0)), Err(GeographicArrayError::NonFinite { axis: Axis::Y, .. })));
+        assert_eq!(GeographicArrayConfig::default().bounds, Bounds::symmetric(MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z));
+
+        //a 2km warehouse with its origin in one corner, next to a 60km metro index
+        let zones: usize = 4096;
+        let warehouse_bounds = Bounds::new((0.0, 2000.0), (-500.0, 1500.0), (0.0, 50.0));
+        let mut warehouse: GeographicArray<usize> = GeographicArray::with_config(GeographicArrayConfig::new(warehouse_bounds, zones));
+        let mut metro: GeographicArray<usize> = GeographicArray::with_config(GeographicArrayConfig::new(Bounds::symmetric(30000.0, 30000.0, 1000.0), zones));
+        assert_eq!(warehouse.bounds().normalise_zero_to_one(&Axis::X, 1000.0), 0.5);
+        assert_eq!(warehouse.bounds().normalise_negative_one_to_one(&Axis::Y, 500.0), 0.0);
+        assert_eq!(warehouse.config().coordinate_to_index(&Axis::Z, 50.0), zones - 1);
+        assert_eq!(warehouse.try_insert(Vector::new(-1.0, 0.0, 0.0), 0), Err(GeographicArrayError::OutOfBounds { axis: Axis::X, value: -1.0, limit: 0.0 }));
+        assert!(metro.try_insert(Vector::new(40000.0, 0.0, 0.0), 0).is_err());
+
+        let mut rng = rand::thread_rng();
+        let mut inserted: Vec<Vector> = Vec::new();
+        for i in 0..5000 {
+            let value = Vector::new(rng.gen_range(0.0..2000.0), rng.gen_range(-500.0..1500.0), rng.gen_range(0.0..50.0));
+            warehouse.insert(value.clone(), i);
+            metro.insert(Vector::new(value.x * 10.0, value.y * 10.0, value.z), i);
+            inserted.push(value);
+        }
+        //the zones are spread over the warehouse rather than the default 131km
+        assert_eq!(warehouse.get_index_vector(EntryId::new(0, 0)).unwrap().x, (zones as f64 * inserted[0].x / 2000.0 - 1.0) as usize);
+
+        let centre = Vector::new(rng.gen_range(0.0..2000.0), rng.gen_range(-500.0..1500.0), rng.gen_range(0.0..50.0));
+        assert_exact(&warehouse, &inserted, &centre, Euclidean, 25, 100.0);
+        let everything = warehouse.find_in_box(&Vector::new(0.0, -500.0, 0.0), &Vector::new(2000.0, 1500.0, 50.0));
+        assert_eq!(everything.len(), 5000);
+        assert_eq!(metro.find_in_box(&Vector::new(0.0, -5000.0, 0.0), &Vector::new(20000.0, 15000.0, 50.0)).len(), 5000);
+    }
+
+    #[test]
+    fn test_bounds_wider_than_default() {
+        //a 400km region, well past the default +-65536
+        let config = GeographicArrayConfig::new(Bounds::symmetric(200000.0, 200000.0, 1000.0), 4096);
+        let mut region: GeographicArray<&str> = GeographicArray::with_config(config);
+        let far = Vector::try_new(150000.0, -180000.0, 0.0).unwrap();
+        let id = region.insert(far.clone(), "far");
+        region.insert(Vector::new(0.0, 0.0, 0.0), "origin");
+        assert_eq!(region.get_index_vector(id), Some(&IndexVector::from_vector(&far, &config)));
+        assert_eq!(region.find_k_nearest(&Vector::new(149000.0, -180000.0, 0.0), 1).first().unwrap().id(), id);
+        assert_eq!(region.find_nearest(&Vector::new(150100.0, -180000.0, 0.0)).first().unwrap().distance(), 100.0);
+        assert_eq!(region.find_within_radius(&Vector::new(100000.0, -100000.0, 0.0), 100000.0).len(), 1);
+        assert_eq!(region.find_in_box(&Vector::new(100000.0, -200000.0, -10.0), &Vector::new(200000.0, 0.0, 10.0)).len(), 1);
+        assert!(matches!(region.try_insert(Vector::new(250000.0, 0.0, 0.0), "past the edge"), Err(GeographicArrayError::OutOfBounds { axis: Axis::X, limit, .. }) if limit == 200000.0));
+    }
+
+    #[test]
+    fn test_runtime_zone_count() {
+        assert!(matches!(GeographicArray::<()>::try_with_config(GeographicArrayConfig::new(Bounds::default(), 0)), Err(GeographicArrayError::InvalidZoneCount { axis: Axis::X, zones: 0 })));
+        let doubled = GeographicArrayConfig::new(Bounds::default(), ZONES_USIZE * 2);
+        assert_eq!(doubled.coordinate_to_index(&Axis::X, MAX_RADIUS_METERS_X), ZONES_USIZE * 2 - 1);
+        assert_eq!(doubled.zone_width(&Axis::X) * 2.0, GeographicArrayConfig::default().zone_width(&Axis::X));
+
+        let mut rng = rand::thread_rng();
+        for zones in [1, 7, 1000] {
+            let mut geographic_array: GeographicArray<usize> = GeographicArray::new(zones);
+            assert_eq!(geographic_array.x.len(), zones);
+            assert_eq!(geographic_array.config().max_index(&Axis::Z), zones - 1);
+            assert!(AxisRange::try_new(geographic_array.config(), &Axis::Y, Some((0, zones))).is_err());
+            assert_eq!(AxisRange::new(geographic_array.config(), &Axis::Y, None).len(), zones);
+
+            let corner = Vector::new(MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z);
+            let edge = geographic_array.insert(corner.clone(), 0);
+            assert_eq!(geographic_array.get_index_vector(edge), Some(&IndexVector::new(zones - 1, zones - 1, zones - 1)));
+            let mut inserted: Vec<Vector> = vec![corner];
+            inserted.extend(insert_random(&mut geographic_array, 2999, &mut rng));
+            //every zone that was filled is one the searches can reach
+            assert_eq!(geographic_array.x.iter().map(|zone| zone.len()).sum::<usize>(), 3000);
+
+            let centre = Vector::generate_random_seeded(&mut rng);
+            assert_exact(&geographic_array, &inserted, &centre, Euclidean, 10, 10000.0);
+            assert_eq!(geographic_array.nearest_iter(&centre).count(), 3000);
+            assert_eq!(geographic_array.find_in_box(&Vector::new(-MAX_RADIUS_METERS_X, -MAX_RADIUS_METERS_Y, -MAX_RADIUS_METERS_Z), &Vector::new(MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z)).len(), 3000);
+            let closest: f64 = inserted.iter().map(|value| distance_between(value, &centre)).fold(f64::INFINITY, f64::min);
+            assert!(!geographic_array.find_nearest(&centre).is_empty() || closest > 5000.0);
+        }
+    }
+
+    #[test]
+    fn test_zone_counts_per_axis() {
+        let config = GeographicArrayConfig::with_zones(Bounds::default(), 4096, 2048, 64);
+        assert!(matches!(GeographicArray::<()>::try_with_config(GeographicArrayConfig::with_zones(Bounds::default(), 4096, 2048, 0)), Err(GeographicArrayError::InvalidZoneCount { axis: Axis::Z, zones: 0 })));
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::with_config(config);
+        assert_eq!((geographic_array.x.len(), geographic_array.y.len(), geographic_array.z.len()), (4096, 2048, 64));
+        assert_eq!(AxisRange::new(&config, &Axis::Y, None).len(), 2048);
+        assert_eq!(AxisRange::new(&config, &Axis::Z, None).len(), 64);
+        assert!(AxisRange::try_new(&config, &Axis::Z, Some((0, 64))).is_err());
+        assert!(AxisRange::try_new(&config, &Axis::X, Some((0, 64))).is_ok());
+        assert_eq!(config.zone_width(&Axis::X), 32.0);
+        assert_eq!(config.zone_width(&Axis::Z), 1024.0);
+
+        let corner = geographic_array.insert(Vector::new(MAX_RADIUS_METERS_X, MAX_RADIUS_METERS_Y, MAX_RADIUS_METERS_Z), 0);
+        assert_eq!(geographic_array.get_index_vector(corner), Some(&IndexVector::new(4095, 2047, 63)));
+
+        let mut rng = rand::thread_rng();
+        let mut ids: Vec<EntryId> = vec![corner];
+        for i in 1..3000 {
+            ids.push(geographic_array.insert(Vector::generate_random_seeded(&mut rng), i));
+        }
+        //half of the points move, some of them between zones on one axis but not another
+        for id in ids.iter().step_by(2) {
+            assert!(geographic_array.update_position(*id, Vector::generate_random_seeded(&mut rng)));
+        }
+        let inserted: Vec<Vector> = ids.iter().map(|id| geographic_array.get(*id).unwrap().clone()).collect();
+
+        let centre = Vector::generate_random_seeded(&mut rng);
+        assert_exact(&geographic_array, &inserted, &centre, Euclidean, 100, 15000.0);
+        let min = Vector::new((centre.x - 20000.0).max(-MAX_RADIUS_METERS_X), (centre.y - 20000.0).max(-MAX_RADIUS_METERS_Y), -MAX_RADIUS_METERS_Z);
+        let max = Vector::new((centre.x + 20000.0).min(MAX_RADIUS_METERS_X), (centre.y + 20000.0).min(MAX_RADIUS_METERS_Y), MAX_RADIUS_METERS_Z);
+        let in_box: usize = inserted.iter().filter(|value| value.x >= min.x && value.x <= max.x && value.y >= min.y && value.y <= max.y).count();
+        assert_eq!(geographic_array.find_in_box(&min, &max).len(), in_box);
+    }
+
+    #[test]
+    fn test_distance_metrics() {
+        fn check<M: DistanceMetric + Clone + 'static>(geographic_array: &GeographicArray<usize>, inserted: &[Vector], centre: &Vector, metric: M) {
+            assert_exact(geographic_array, inserted, centre, metric.clone(), 50, 8000.0);
+            assert!(geographic_array.find_nearest_with_metric(centre, metric).iter().all(|hit| hit.distance() <= 5000.0));
+        }
+
+        assert!(matches!(Weighted::try_new(1.0, -1.0, 1.0), Err(GeographicArrayError::InvalidWeight { axis: Axis::Y, .. })));
+        assert!(Weighted::try_new(1.0, 1.0, f64::NAN).is_err());
+
+        //a drone planner weighting altitude three times as heavily as horizontal distance
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::with_config(small_config());
+        let origin = Vector::new(0.0, 0.0, 0.0);
+        let across = geographic_array.insert(Vector::new(100.0, 0.0, 0.0), 0);
+        let above = geographic_array.insert(Vector::new(0.0, 0.0, 40.0), 1);
+        assert_eq!(geographic_array.find_k_nearest(&origin, 1).first().unwrap().id(), above);
+        let weighted = geographic_array.find_k_nearest_with_metric(&origin, 2, Weighted::new(1.0, 1.0, 3.0));
+        assert_eq!(weighted.first().unwrap().id(), across);
+        assert_eq!(weighted.last().unwrap().distance(), 120.0);
+        assert_eq!(geographic_array.find_within_radius_with_metric(&origin, 110.0, Weighted::new(1.0, 1.0, 3.0)).len(), 1);
+        assert_eq!(geographic_array.find_within_radius_with_metric(&origin, 100.0, Manhattan).len(), 2);
+
+        let mut rng = rand::thread_rng();
+        let mut inserted: Vec<Vector> = vec![Vector::new(100.0, 0.0, 0.0), Vector::new(0.0, 0.0, 40.0)];
+        inserted.extend(insert_random(&mut geographic_array, 2998, &mut rng));
+        let centre = Vector::generate_random_seeded(&mut rng);
+        check(&geographic_array, &inserted, &centre, Euclidean);
+        check(&geographic_array, &inserted, &centre, Manhattan);
+        check(&geographic_array, &inserted, &centre, Chebyshev);
+        check(&geographic_array, &inserted, &centre, Weighted::new(1.0, 1.0, 3.0));
+        //X is the axis walked, a weight of 0 there means nothing can be ruled out and everything is looked at
+        check(&geographic_array, &inserted, &centre, Weighted::new(0.0, 2.0, 0.5));
+    }
+
+    #[test]
+    fn test_find_nearest_ground() {
+        let mut geographic_array: GeographicArray<&str> = GeographicArray::default();
+        let origin = Vector::new(0.0, 0.0, 0.0);
+        let upstairs = geographic_array.insert(Vector::new(30.0, 0.0, 40.0), "upstairs");
+        let same_floor = geographic_array.insert(Vector::new(0.0, 45.0, 0.0), "same floor");
+        geographic_array.insert(Vector::new(0.0, 6000.0, 0.0), "too far");
+
+        //as the crow flies the point on the same floor is closer, along the ground the one upstairs is
+        assert_eq!(geographic_array.find_nearest(&origin).first().unwrap().id(), same_floor);
+        let ground = geographic_array.find_nearest_ground(&origin, None);
+        assert_eq!(ground.first().unwrap().id(), upstairs);
+        assert_eq!(ground.first().unwrap().distance(), 30.0);
+        assert_eq!(ground.len(), 2);
+        let banded = geographic_array.find_nearest_ground(&origin, Some(10.0));
+        assert_eq!(banded.len(), 1);
+        assert_eq!(*banded.first().unwrap().value(), "same floor");
+        assert_eq!(Ground.distance(&origin, &Vector::new(3.0, 4.0, 1000.0)), 5.0);
+
+        //the first hit is the exact nearest in the band, nothing outside the band or past the threshold is returned
+        let mut rng = rand::thread_rng();
+        let mut inserted: Vec<Vector> = vec![Vector::new(30.0, 0.0, 40.0), Vector::new(0.0, 45.0, 0.0), Vector::new(0.0, 6000.0, 0.0)];
+        for _ in 0..10000 {
+            let value = Vector::generate_random_seeded(&mut rng);
+            geographic_array.insert(value.clone(), "random");
+            inserted.push(value);
+        }
+        let centre = Vector::generate_random_seeded(&mut rng);
+        let found = geographic_array.find_nearest_ground(&centre, Some(2000.0));
+        let expected = inserted.iter()
+            .filter(|value| (value.z - centre.z).abs() <= 2000.0)
+            .map(|value| Ground.distance(value, &centre))
+            .filter(|distance| *distance <= 5000.0)
+            .min_by(f64::total_cmp);
+        assert_eq!(found.first().map(|hit| hit.distance()), expected);
+        for hit in &found {
+            assert_eq!(hit.distance(), Ground.distance(hit.vector(), &centre));
+            assert!(hit.distance() <= 5000.0);
+            assert!((hit.vector().z - centre.z).abs() <= 2000.0);
+        }
+        assert!(found.iter().zip(found.iter().skip(1)).all(|(one, two)| one.distance() <= two.distance()));
+    }
+
+    #[test]
+    fn test_query_options() {
+        let mut geographic_array: GeographicArray<&str> = GeographicArray::default();
+        let origin = Vector::new(0.0, 0.0, 0.0);
+        let close = geographic_array.insert(Vector::new(30.0, 0.0, 0.0), "close");
+        let across_town = geographic_array.insert(Vector::new(0.0, 12000.0, 0.0), "across town");
+        geographic_array.insert(Vector::new(0.0, -40000.0, 0.0), "too far");
+
+        //a 50m and a 20km limit against the same data, the default is still DISTANCE_THRESHOLD
+        let nearby = geographic_array.find_nearest_with(&origin, &QueryOptions::new().max_distance(50.0));
+        assert_eq!(nearby.iter().map(|hit| hit.id()).collect::<Vec<EntryId>>(), vec![close]);
+        let far = Vector::new(0.0, 18000.0, 0.0);
+        assert!(geographic_array.find_nearest(&far).is_empty());
+        let found = geographic_array.find_nearest_with(&far, &QueryOptions::new().max_distance(20000.0));
+        assert_eq!(found.first().unwrap().id(), across_town);
+        assert!(geographic_array.find_nearest_with(&origin, &QueryOptions::new().max_distance(10.0)).is_empty());
+        let ground = geographic_array.find_nearest_ground_with(&far, None, &QueryOptions::new().max_distance(20000.0));
+        assert_eq!(ground.first().unwrap().id(), across_town);
+
+        //limits for queries that have none of their own
+        let k_nearest = geographic_array.find_k_nearest_with(&origin, 3, &QueryOptions::new().max_distance(20000.0));
+        assert_eq!(k_nearest.len(), 2);
+        assert_eq!(geographic_array.find_k_nearest_with(&origin, 3, &QueryOptions::new().max_results(1)).len(), 1);
+        assert_eq!(geographic_array.nearest_iter_with(&origin, QueryOptions::new().max_distance(50.0)).count(), 1);
+        assert_eq!(geographic_array.nearest_iter_with(&origin, QueryOptions::new().max_results(2)).count(), 2);
+        assert_eq!(geographic_array.find_within_radius_with(&origin, 50000.0, &QueryOptions::new().max_distance(20000.0)).len(), 2);
+        assert_eq!(geographic_array.find_within_radius_with(&origin, 50000.0, &QueryOptions::new().max_results(1)).first().unwrap().id(), close);
+        let (min, max) = (Vector::new(-100.0, -50000.0, -100.0), Vector::new(100.0, 50000.0, 100.0));
+        assert_eq!(geographic_array.find_in_box(&min, &max).len(), 3);
+        assert_eq!(geographic_array.find_in_box_with(&min, &max, &QueryOptions::new().max_distance(20000.0)).len(), 2);
+
+        //everything else the options carry, with any axis walked
+        let options = QueryOptions::new().filter(|_, value: &&str| *value != "close").max_distance(20000.0);
+        assert_eq!(geographic_array.find_nearest_with(&origin, &options).first().unwrap().id(), across_town);
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            let options = QueryOptions::new().axis_selection(AxisSelection::Axis(axis)).metric(Manhattan);
+            assert_eq!(geographic_array.find_nearest_with(&origin, &options).first().unwrap().id(), close);
+            assert_eq!(geographic_array.find_within_radius_with(&origin, 15000.0, &options).len(), 2);
+            assert_eq!(geographic_array.find_k_nearest_with(&origin, 3, &options).len(), 3);
+        }
+
+        //the defaults give the same results as the queries without options
+        let mut rng = rand::thread_rng();
+        for _ in 0..5000 {
+            geographic_array.insert(Vector::generate_random_seeded(&mut rng), "random");
+        }
+        let centre = Vector::generate_random_seeded(&mut rng);
+        let ids = |result: crate::QueryResult<'_, &str>| result.iter().map(|hit| hit.id()).collect::<Vec<EntryId>>();
+        assert_eq!(ids(geographic_array.find_nearest_with(&centre, &QueryOptions::default())), ids(geographic_array.find_nearest(&centre)));
+        assert_eq!(ids(geographic_array.find_k_nearest_with(&centre, 10, &QueryOptions::default())), ids(geographic_array.find_k_nearest(&centre, 10)));
+        assert_eq!(ids(geographic_array.find_within_radius_with(&centre, 3000.0, &QueryOptions::default())), ids(geographic_array.find_within_radius(&centre, 3000.0)));
+    }
+
+    #[test]
+    fn test_geodetic_origin() {
+        //east and north along the equator, a hundredth of a degree of longitude is a*sin(0.01 degrees) meters from the origin
+        let local_frame = LocalFrame::new(Geodetic::new(0.0, 0.0, 0.0));
+        let east = local_frame.to_local(&Geodetic::new(0.0, 0.01, 0.0));
+        assert!((east.x - 6378137.0 * 0.01_f64.to_radians().sin()).abs() < 1e-6);
+        assert!(east.y.abs() < 1e-6);
+        assert!(east.z < 0.0 && east.z > -0.1);
+        let north = local_frame.to_local(&Geodetic::new(0.01, 0.0, 0.0));
+        assert!(north.x.abs() < 1e-6 && (north.y - 1105.7).abs() < 1.0);
+        let up = local_frame.to_local(&Geodetic::new(0.0, 0.0, 25.0));
+        assert!(up.x.abs() < 1e-6 && up.y.abs() < 1e-6 && (up.z - 25.0).abs() < 1e-6);
+
+        let mut geographic_array: GeographicArray<&str> = GeographicArray::default().with_geodetic_origin(51.4779, -0.0015, 45.0);
+        let origin = geographic_array.geodetic_to_vector(&Geodetic::new(51.4779, -0.0015, 45.0)).unwrap();
+        assert!(origin.x.abs() < 1e-6 && origin.y.abs() < 1e-6 && origin.z.abs() < 1e-6);
+        let pier = geographic_array.insert_geodetic(&Geodetic::new(51.4827, -0.0096, 5.0), "pier");
+        let park = geographic_array.insert_geodetic(&Geodetic::new(51.4769, 0.0005, 40.0), "park");
+        geographic_array.insert_geodetic(&Geodetic::new(51.6, 0.3, 100.0), "too far");
+
+        let found = geographic_array.find_nearest_geodetic(&Geodetic::new(51.4775, 0.0, 40.0));
+        assert_eq!(found.first().unwrap().id(), park);
+        let geodetic = found.first().unwrap().geodetic().unwrap();
+        assert!((geodetic.latitude - 51.4769).abs() < 1e-9 && (geodetic.longitude - 0.0005).abs() < 1e-9 && (geodetic.altitude - 40.0).abs() < 1e-6);
+        let stored = geographic_array.get_geodetic(pier).unwrap();
+        assert!((stored.latitude - 51.4827).abs() < 1e-9 && (stored.longitude + 0.0096).abs() < 1e-9 && (stored.altitude - 5.0).abs() < 1e-6);
+
+        //distances are still meters in the local frame
+        let distance = found.first().unwrap().distance();
+        assert!((distance - 75.3).abs() < 0.5);
+
+        //nothing to convert against without an origin
+        let mut without_origin: GeographicArray<&str> = GeographicArray::default();
+        assert_eq!(without_origin.try_insert_geodetic(&Geodetic::new(51.4779, -0.0015, 45.0), "pier"), Err(GeographicArrayError::NoGeodeticOrigin));
+        assert!(without_origin.try_find_nearest_geodetic(&Geodetic::new(51.4779, -0.0015, 45.0)).is_err());
+        without_origin.insert(Vector::new(1.0, 1.0, 1.0), "local");
+        assert!(without_origin.find_nearest(&Vector::new(0.0, 0.0, 0.0)).first().unwrap().geodetic().is_none());
+        assert!(matches!(Geodetic::try_new(91.0, 0.0, 0.0), Err(GeographicArrayError::InvalidGeodetic { .. })));
+        assert!(Geodetic::try_new(0.0, f64::NAN, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_ecef_conversions() {
+        let distance = |one: &Ecef, two: &Ecef| ((one.x - two.x).powi(2) + (one.y - two.y).powi(2) + (one.z - two.z).powi(2)).sqrt();
+
+        //the axes of the ellipsoid
+        assert!(distance(&geodetic_to_ecef(&Geodetic::new(0.0, 0.0, 0.0)), &Ecef::new(6378137.0, 0.0, 0.0)) < 1e-3);
+        assert!(distance(&geodetic_to_ecef(&Geodetic::new(0.0, 90.0, 0.0)), &Ecef::new(0.0, 6378137.0, 0.0)) < 1e-3);
+        assert!(distance(&geodetic_to_ecef(&Geodetic::new(90.0, 0.0, 0.0)), &Ecef::new(0.0, 0.0, 6356752.314245)) < 1e-3);
+        let south_pole = ecef_to_geodetic(&Ecef::new(0.0, 0.0, -6356852.314245));
+        assert!((south_pole.latitude + 90.0).abs() < 1e-9 && (south_pole.altitude - 100.0).abs() < 1e-3);
+
+        //round trips are within a millimeter from below sea level out to satellite orbits
+        let mut rng = rand::thread_rng();
+        for _ in 0..10000 {
+            let altitude: f64 = if rng.gen_bool(0.9) { rng.gen_range(-500.0..10000.0) } else { rng.gen_range(10000.0..30000000.0) };
+            let geodetic = Geodetic::new(rng.gen_range(-90.0..=90.0), rng.gen_range(-180.0..=180.0), altitude);
+            let ecef = geodetic_to_ecef(&geodetic);
+            let round_trip = ecef_to_geodetic(&ecef);
+            assert!(distance(&geodetic_to_ecef(&round_trip), &ecef) < 1e-3);
+            assert!((round_trip.altitude - geodetic.altitude).abs() < 1e-3);
+
+            let local_frame = LocalFrame::new(Geodetic::new(rng.gen_range(-90.0..=90.0), rng.gen_range(-180.0..=180.0), rng.gen_range(-100.0..3000.0)));
+            let vector = local_frame.ecef_to_local(&ecef);
+            assert!(distance(&local_frame.local_to_ecef(&vector), &ecef) < 1e-3);
+            let local = Vector::generate_random_seeded(&mut rng);
+            let back = local_frame.ecef_to_local(&local_frame.local_to_ecef(&local));
+            assert!(distance_between(&back, &local) < 1e-3);
+            let back = local_frame.to_local(&local_frame.to_geodetic(&local));
+            assert!(distance_between(&back, &local) < 1e-3);
+        }
+
+        //the local frame keeps distances, a receiver 1km up from the origin is 1km up
+        let mut geographic_array: GeographicArray<&str> = GeographicArray::default().with_geodetic_origin(-33.8568, 151.2153, 0.0);
+        let origin_ecef = geodetic_to_ecef(&Geodetic::new(-33.8568, 151.2153, 0.0));
+        let receiver = geodetic_to_ecef(&Geodetic::new(-33.8568, 151.2153, 1000.0));
+        let vector = geographic_array.ecef_to_vector(&receiver).unwrap();
+        assert!(distance_between(&vector, &Vector::new(0.0, 0.0, 1000.0)) < 1e-3);
+        let id = geographic_array.insert_ecef(&receiver, "receiver");
+        assert!(distance(&geographic_array.vector_to_ecef(geographic_array.get(id).unwrap()).unwrap(), &receiver) < 1e-3);
+        assert_eq!(geographic_array.find_nearest(&Vector::new(0.0, 0.0, 0.0)).first().unwrap().id(), id);
+        assert!((distance(&origin_ecef, &receiver) - 1000.0).abs() < 1e-3);
+
+        assert!(matches!(Ecef::try_new(f64::NAN, 0.0, 0.0), Err(GeographicArrayError::NonFinite { axis: Axis::X, .. })));
+        assert_eq!(GeographicArray::<&str>::default().ecef_to_vector(&receiver), Err(GeographicArrayError::NoGeodeticOrigin));
+    }
+
+    #[test]
+    fn test_utm_and_mgrs() {
+        //on the central meridian the easting is the false easting and the northing is the scaled meridian arc
+        let utm = Utm::from_geodetic(&Geodetic::new(45.0, 3.0, 0.0)).unwrap();
+        assert_eq!((utm.zone, utm.hemisphere), (31, Hemisphere::North));
+        assert!((utm.easting - 500000.0).abs() < 1e-3 && (utm.northing - 4982950.400).abs() < 1e-3);
+        let utm = Utm::from_geodetic(&Geodetic::new(-33.8568, 151.2153, 0.0)).unwrap();
+        assert_eq!((utm.zone, utm.hemisphere), (56, Hemisphere::South));
+        assert_eq!((utm_zone(60.0, 5.0), utm_zone(78.0, 15.0), utm_zone(0.0, 180.0), utm_zone(0.0, -180.0)), (32, 33, 1, 1));
+
+        //round trips within a tenth of a millimeter, in the point's own zone and the zones either side of it while the easting stays on the grid
+        let mut rng = rand::thread_rng();
+        for _ in 0..10000 {
+            let geodetic = Geodetic::new(rng.gen_range(-80.0..=84.0), rng.gen_range(-180.0..180.0), 0.0);
+            let own_zone = utm_zone(geodetic.latitude, geodetic.longitude);
+            for zone in [own_zone, own_zone % 60 + 1, (own_zone + 58) % 60 + 1] {
+                let utm = match Utm::from_geodetic_in_zone(&geodetic, zone) {
+                    Ok(utm) => utm,
+                    Err(error) => {
+                        assert!(zone != own_zone && matches!(error, GeographicArrayError::InvalidGridReference(_)));
+                        continue;
+                    },
+                };
+                let round_trip = utm.to_geodetic(0.0);
+                let (one, two) = (geodetic_to_ecef(&geodetic), geodetic_to_ecef(&round_trip));
+                assert!(((one.x - two.x).powi(2) + (one.y - two.y).powi(2) + (one.z - two.z).powi(2)).sqrt() < 1e-4);
+            }
+            let mgrs = Mgrs::from_geodetic(&geodetic, 5).unwrap();
+            let parsed: Mgrs = mgrs.to_string().parse().unwrap();
+            assert_eq!(parsed.to_string(), mgrs.to_string());
+            let utm = Utm::from_geodetic(&geodetic).unwrap();
+            let from_mgrs = parsed.to_utm().unwrap();
+            assert_eq!((from_mgrs.zone, from_mgrs.hemisphere), (utm.zone, utm.hemisphere));
+            assert!((from_mgrs.easting - utm.easting).abs() <= 0.5 && (from_mgrs.northing - utm.northing).abs() <= 0.5);
+        }
+
+        //reading and writing
+        let utm: Utm = "31N 448251 5411932".parse().unwrap();
+        assert_eq!(utm, Utm::new(31, Hemisphere::North, 448251.0, 5411932.0));
+        assert_eq!("31 n 448251 5411932".parse::<Utm>().unwrap(), utm);
+        assert_eq!(utm.to_string(), "31N 448251 5411932");
+        let mgrs: Mgrs = "18S UJ 23383 08451".parse().unwrap();
+        assert_eq!(mgrs.to_string(), "18SUJ2338308451");
+        assert_eq!(mgrs.to_utm().unwrap(), Utm::new(18, Hemisphere::North, 323383.5, 4308451.5));
+        let coarse: Mgrs = "18SUJ2308".parse().unwrap();
+        assert_eq!((coarse.precision, coarse.easting, coarse.northing), (2, 23500.0, 8500.0));
+        assert_eq!(Mgrs::from_geodetic(&mgrs.to_geodetic(0.0).unwrap(), 2).unwrap().to_string(), "18SUJ2308");
+        //off the grid, eastings are 100km to 900km and northings run from the equator or 80 south up to 84 north or the equator
+        for invalid in ["31X 448251 5411932", "61N 448251 5411932", "31N 448251", "north 448251 5411932", "1N 0 0", "31N -448251 5411932", "31N 900000 5411932", "31N 448251 -1", "31N 448251 9500000", "31S 448251 500000", "31S 448251 10000001"] {
+            assert!(matches!(invalid.parse::<Utm>(), Err(GeographicArrayError::InvalidGridReference(_))));
+        }
+        for invalid in ["18SUJ233830845", "18SIJ2338308451", "18SAJ2338308451", "0SUJ23", "18S", "SUJ23"] {
+            assert!(matches!(invalid.parse::<Mgrs>(), Err(GeographicArrayError::InvalidGridReference(_))));
+        }
+        assert!(matches!(Utm::from_geodetic(&Geodetic::new(85.0, 0.0, 0.0)), Err(GeographicArrayError::OutsideUtmCoverage { .. })));
+        assert!("31N 100000 0".parse::<Utm>().is_ok() && "31S 899999 10000000".parse::<Utm>().is_ok());
+        assert!(matches!(Utm::from_geodetic_in_zone(&Geodetic::new(0.0, 3.0, 0.0), 33), Err(GeographicArrayError::InvalidGridReference(_))));
+        //the westernmost and easternmost columns of zone 1 at the equator, where the zone is widest
+        assert_eq!(Mgrs::from_geodetic(&Geodetic::new(0.0, -179.99, 0.0), 0).unwrap().column, 'A');
+        assert_eq!(Mgrs::from_geodetic(&Geodetic::new(0.0, -174.01, 0.0), 0).unwrap().column, 'H');
+
+        //an origin at the edge of zone 31 with a point reported in zone 32
+        let mut geographic_array: GeographicArray<&str> = GeographicArray::default().with_geodetic_origin(50.0, 5.99, 0.0);
+        let across = Geodetic::new(50.0, 6.01, 0.0);
+        let reported = Utm::from_geodetic(&across).unwrap();
+        assert_eq!(reported.zone, 32);
+        let id = geographic_array.insert_utm(&reported, 0.0, "across the zone edge");
+        let origin_utm = Utm::from_geodetic(&Geodetic::new(50.0, 5.99, 0.0)).unwrap();
+        assert_eq!(origin_utm.zone, 31);
+        let found = geographic_array.find_nearest_utm(&origin_utm, 0.0);
+        let hit = found.first().unwrap();
+        assert_eq!(hit.id(), id);
+        let expected = geographic_array.geodetic_to_vector(&across).unwrap();
+        assert!(distance_between(hit.vector(), &expected) < 1e-3);
+        assert!((hit.distance() - distance_between(&expected, &Vector::new(0.0, 0.0, 0.0))).abs() < 1e-3);
+        assert_eq!(hit.utm().unwrap().zone, 32);
+        assert_eq!(hit.mgrs(5).unwrap().to_string(), Mgrs::from_geodetic(&across, 5).unwrap().to_string());
+        let mgrs = geographic_array.vector_to_mgrs(hit.vector(), 3).unwrap();
+        assert_eq!(geographic_array.find_nearest_mgrs(&mgrs, 0.0).first().unwrap().id(), id);
+        assert!(geographic_array.try_insert_mgrs(&mgrs, 0.0, "same square").is_ok());
+        assert_eq!(GeographicArray::<&str>::default().try_find_nearest_utm(&origin_utm, 0.0), Err(GeographicArrayError::NoGeodeticOrigin));
+    }
+
+    #[test]
+    fn test_grid_reference_queries() {
+        //across the edge of zones 31 and 32
+        let mut geographic_array: GeographicArray<usize> = GeographicArray::with_config(small_config()).with_geodetic_origin(50.3, 6.0, 0.0);
+        let mut rng = rand::thread_rng();
+        let mut ids: Vec<EntryId> = Vec::new();
+        for i in 0..3000 {
+            ids.push(geographic_array.insert_geodetic(&Geodetic::new(rng.gen_range(50.0..50.6), rng.gen_range(5.6..6.4), rng.gen_range(0.0..2000.0)), i));
+        }
+        let stored: Vec<(EntryId, Geodetic)> = ids.iter().map(|id| (*id, geographic_array.get_geodetic(*id).unwrap())).collect();
+        let matching = |found: QueryResult<'_, usize>, expected: &dyn Fn(&Geodetic) -> bool| {
+            let mut found: Vec<EntryId> = found.iter().map(|hit| hit.id()).collect();
+            let mut wanted: Vec<EntryId> = stored.iter().filter(|(_, geodetic)| expected(geodetic)).map(|(id, _)| *id).collect();
+            found.sort();
+            wanted.sort();
+            assert!(!wanted.is_empty());
+            assert_eq!(found, wanted);
+        };
+        let chord = |one: &Geodetic, two: &Geodetic| {
+            let (one, two) = (geodetic_to_ecef(one), geodetic_to_ecef(two));
+            ((one.x - two.x).powi(2) + (one.y - two.y).powi(2) + (one.z - two.z).powi(2)).sqrt()
+        };
+
+        //nearest and radius from a grid reference are the straight line distance from where it is
+        let utm = Utm::from_geodetic(&Geodetic::new(50.25, 5.95, 0.0)).unwrap();
+        let mgrs: Mgrs = Mgrs::from_geodetic(&Geodetic::new(50.4, 6.1, 0.0), 5).unwrap().to_string().parse().unwrap();
+        for (centre, k_nearest, within) in [
+            (utm.to_geodetic(300.0), geographic_array.find_k_nearest_utm(&utm, 300.0, 10), geographic_array.find_within_radius_utm(&utm, 300.0, 3000.0)),
+            (mgrs.to_geodetic(300.0).unwrap(), geographic_array.find_k_nearest_mgrs(&mgrs, 300.0, 10), geographic_array.find_within_radius_mgrs(&mgrs, 300.0, 3000.0)),
+        ] {
+            let mut expected: Vec<f64> = stored.iter().map(|(_, geodetic)| chord(geodetic, &centre)).collect();
+            expected.sort_by(f64::total_cmp);
+            assert_eq!(k_nearest.len(), 10);
+            for (hit, distance) in k_nearest.iter().zip(&expected) {
+                assert!((hit.distance() - distance).abs() < 1e-6);
+            }
+            assert_eq!(geographic_array.find_k_nearest_geodetic(&centre, 10), k_nearest);
+            matching(within, &|geodetic| chord(geodetic, &centre) <= 3000.0);
+        }
+        assert_eq!(geographic_array.try_find_within_radius_utm(&utm, 0.0, -1.0).err(), Some(GeographicArrayError::InvalidRadius(-1.0)));
+
+        //boxes of latitude and longitude, easting and northing, and MGRS squares
+        matching(geographic_array.find_in_box_geodetic(&Geodetic::new(50.2, 5.9, 0.0), &Geodetic::new(50.35, 6.1, 0.0)), &|geodetic| {
+            (50.2..=50.35).contains(&geodetic.latitude) && (5.9..=6.1).contains(&geodetic.longitude)
+        });
+        let (min, max) = (Utm::new(31, Hemisphere::North, utm.easting - 8000.0, utm.northing - 6000.0), Utm::new(31, Hemisphere::North, utm.easting + 15000.0, utm.northing + 7000.0));
+        matching(geographic_array.find_in_box_utm(&min, &max), &|geodetic| {
+            let utm = Utm::from_geodetic_in_zone(geodetic, 31).unwrap();
+            (min.easting..=max.easting).contains(&utm.easting) && (min.northing..=max.northing).contains(&utm.northing)
+        });
+        assert!(matches!(geographic_array.try_find_in_box_utm(&min, &Utm::new(32, Hemisphere::North, 300000.0, 5570000.0)), Err(GeographicArrayError::InvalidGridReference(_))));
+        for precision in [0, 1] {
+            for square in [mgrs, Mgrs::from_geodetic(&Geodetic::new(50.3, 5.97, 0.0), 5).unwrap()] {
+                let square: Mgrs = Mgrs { precision, ..square }.to_string().parse().unwrap();
+                matching(geographic_array.find_in_mgrs(&square), &|geodetic| Mgrs::from_geodetic(geodetic, precision).unwrap().to_string() == square.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_geohash() {
+        assert_eq!(encode_geohash(&Geodetic::new(57.64911, 10.40744, 0.0), 11).unwrap(), "u4pruydqqvj");
+        let cell = GeohashCell::decode("EZS42").unwrap();
+        assert!((cell.south - 42.583).abs() < 1e-3 && (cell.north - 42.627).abs() < 1e-3);
+        assert!((cell.west + 5.625).abs() < 1e-3 && (cell.east + 5.581).abs() < 1e-3);
+        assert_eq!(encode_geohash(&cell.centre(0.0), 5).unwrap(), "ezs42");
+        assert!(cell.contains(&Geodetic::new(cell.south, cell.west, 0.0)) && !cell.contains(&Geodetic::new(cell.north, cell.west, 0.0)));
+        assert!(GeohashCell::decode("").is_err() && GeohashCell::decode("ezs4a").is_err() && GeohashCell::decode("ezs42ezs42ezs").is_err());
+        assert!(encode_geohash(&Geodetic::new(0.0, 0.0, 0.0), 0).is_err());
+
+        //every point in the cell at any altitude and nothing outside it, checked against the geohash of each point
+        let mut geographic_array: GeographicArray<String> = GeographicArray::default()
+            .with_geodetic_origin(57.64911, 10.40744, 20.0)
+            .with_out_of_bounds_policy(OutOfBoundsPolicy::Overflow);
+        let mut rng = rand::thread_rng();
+        for _ in 0..20000 {
+            let vector = Vector::new(rng.gen_range(-30000.0..30000.0), rng.gen_range(-30000.0..30000.0), rng.gen_range(-2000.0..20000.0));
+            let geohash = geographic_array.vector_to_geohash(&vector, 12).unwrap();
+            geographic_array.insert(vector, geohash);
+        }
+        let far = geographic_array.geodetic_to_vector(&Geodetic::new(57.64911, 12.0, 20.0)).unwrap();
+        geographic_array.insert(far.clone(), geographic_array.vector_to_geohash(&far, 12).unwrap());
+        for length in 1..=6 {
+            let vector = Vector::generate_random_seeded(&mut rng);
+            let prefix = geographic_array.vector_to_geohash(&vector, length).unwrap();
+            let found = geographic_array.find_in_geohash(&prefix);
+            let mut found: Vec<EntryId> = found.iter().map(|hit| hit.id()).collect();
+            found.sort();
+            let mut expected: Vec<EntryId> = geographic_array.find_in_box(&Vector::new(-1e9, -1e9, -1e9), &Vector::new(1e9, 1e9, 1e9)).iter()
+                .filter(|hit| hit.value().starts_with(&prefix))
+                .map(|hit| hit.id())
+                .collect();
+            expected.sort();
+            assert_eq!(found, expected);
+        }
+        let hit = geographic_array.find_in_geohash("u4pr").into_vec();
+        assert!(hit.iter().all(|hit| hit.geohash(4).unwrap() == "u4pr"));
+        assert!(geographic_array.find_in_geohash("u").iter().any(|hit| *hit.vector() == far));
+
+        let centre = geographic_array.geohash_to_vector("u4pruydqqvj", 20.0).unwrap();
+        assert!(distance_between(&centre, &Vector::new(0.0, 0.0, 0.0)) < 1.0);
+        assert_eq!(GeographicArray::<String>::default().try_find_in_geohash("u4pr"), Err(GeographicArrayError::NoGeodeticOrigin));
+    }
+
+    #[test]
+    fn test_geographic_world() {
+        let mut world: GeographicWorld<String> = GeographicWorld::new();
+        assert_eq!(world.tile_count(), 0);
+
+        //either side of the border between two tiles at 10 east
+        let west = world.insert(&Geodetic::new(50.0, 9.999, 0.0), "west".to_string());
+        let east = world.insert(&Geodetic::new(50.0, 10.0001, 0.0), "east".to_string());
+        assert_eq!(world.tile_count(), 2);
+        assert_ne!(west.tile, east.tile);
+        let nearest = world.find_nearest(&Geodetic::new(50.0, 9.9999, 0.0)).unwrap();
+        assert_eq!(nearest.id(), east);
+        assert!((nearest.geodetic().longitude - 10.0001).abs() < 1e-9);
+        let within: Vec<WorldEntryId> = world.find_within_radius(&Geodetic::new(50.0, 9.9999, 0.0), 100.0).iter().map(|hit| hit.id()).collect();
+        assert_eq!(within, vec![east, west]);
+
+        //across 180 east and over the north pole
+        let date_line = world.insert(&Geodetic::new(0.0, -179.9999, 0.0), "date line".to_string());
+        assert_eq!(world.find_nearest(&Geodetic::new(0.0, 179.9999, 0.0)).unwrap().id(), date_line);
+        let pole = world.insert(&Geodetic::new(89.9999, 180.0, 0.0), "pole".to_string());
+        assert_eq!(world.find_within_radius(&Geodetic::new(89.9999, 0.0, 0.0), 50.0).first().unwrap().id(), pole);
+
+        //exact against every point, for tiles nearby and points on other continents
+        let mut rng = rand::thread_rng();
+        let mut inserted: Vec<(WorldEntryId, Ecef)> = Vec::new();
+        for id in [west, east, date_line, pole] {
+            inserted.push((id, geodetic_to_ecef(&world.get(id).unwrap())));
+        }
+        for i in 0..3000 {
+            let geodetic = if i % 10 == 0 {
+                Geodetic::new(rng.gen_range(-90.0..=90.0), rng.gen_range(-180.0..180.0), rng.gen_range(0.0..3000.0))
+            } else {
+                Geodetic::new(rng.gen_range(49.0..51.0), rng.gen_range(9.0..11.0), rng.gen_range(0.0..3000.0))
+            };
+            inserted.push((world.insert(&geodetic, i.to_string()), geodetic_to_ecef(&geodetic)));
+        }
+        //an airliner, the trench floor, the space station and a geostationary satellite are all past the tile altitudes
+        let airliner = Geodetic::new(50.2, 10.3, 11000.0);
+        let trench = Geodetic::new(11.35, 142.2, -10900.0);
+        let station = Geodetic::new(50.0, 10.0, 400000.0);
+        let geostationary = Geodetic::new(0.0, 10.0, 35786000.0);
+        let mut high: Vec<WorldEntryId> = Vec::new();
+        for geodetic in [airliner, trench, station, geostationary] {
+            let id = world.insert(&geodetic, "high".to_string());
+            assert!((world.get(id).unwrap().altitude - geodetic.altitude).abs() < 1e-3);
+            inserted.push((id, geodetic_to_ecef(&geodetic)));
+            high.push(id);
+        }
+        assert_eq!(world.find_nearest(&Geodetic::new(50.0, 10.0, 399000.0)).unwrap().id(), high[2]);
+        assert_eq!(world.find_nearest(&Geodetic::new(0.0, 10.0, 35000000.0)).unwrap().id(), high[3]);
+        assert_eq!(world.find_nearest(&Geodetic::new(11.35, 142.2, -10000.0)).unwrap().id(), high[1]);
+        assert!(world.find_within_radius(&Geodetic::new(50.0, 10.0, 0.0), 400100.0).iter().any(|hit| hit.id() == high[2]));
+        assert_eq!(world.find_within_radius(&Geodetic::new(50.2, 10.3, 10000.0), 1001.0).first().unwrap().id(), high[0]);
+
+        //tiles are sized to half a degree, not the default bounds
+        let config = *world.tile(&world.tile_key(&station)).unwrap().config();
+        assert_eq!(config, GeographicWorld::<()>::default_tile_config(WORLD_TILE_DEGREES));
+        assert!(config.zones_x < 512 && config.zones_y < 512 && config.zones_z < 128);
+        assert_eq!(world.len(), inserted.len());
+        let distance = |one: &Ecef, two: &Ecef| ((one.x - two.x).powi(2) + (one.y - two.y).powi(2) + (one.z - two.z).powi(2)).sqrt();
+        for i in 0..20 {
+            let centre = if i % 4 == 0 {
+                Geodetic::new(rng.gen_range(-90.0..=90.0), rng.gen_range(-180.0..180.0), 0.0)
+            } else {
+                Geodetic::new(rng.gen_range(49.0..51.0), rng.gen_range(9.0..11.0), 0.0)
+            };
+            let centre_ecef = geodetic_to_ecef(&centre);
+            let mut expected: Vec<(f64, WorldEntryId)> = inserted.iter().map(|(id, ecef)| (distance(ecef, &centre_ecef), *id)).collect();
+            expected.sort_by(|one, two| one.0.total_cmp(&two.0));
+
+            let k_nearest = world.find_k_nearest(&centre, 10);
+            assert_eq!(k_nearest.len(), 10);
+            for (hit, (expected_distance, _)) in k_nearest.iter().zip(&expected) {
+                assert!((hit.distance() - expected_distance).abs() < 1e-3);
+            }
+            let radius: f64 = 20000.0;
+            let mut found: Vec<WorldEntryId> = world.find_within_radius(&centre, radius).iter().map(|hit| hit.id()).collect();
+            let mut within: Vec<WorldEntryId> = expected.iter().take_while(|(distance, _)| *distance <= radius).map(|(_, id)| *id).collect();
+            found.sort();
+            within.sort();
+            assert_eq!(found, within);
+        }
+
+        let (geodetic, value) = world.remove(west).unwrap();
+        assert_eq!(value, "west");
+        assert!((geodetic.latitude - 50.0).abs() < 1e-9 && (geodetic.longitude - 9.999).abs() < 1e-9);
+        assert!(!world.contains(west) && world.get(west).is_none());
+        assert_eq!(world.len(), inserted.len() - 1);
+        assert_eq!(world.find_k_nearest(&Geodetic::new(50.0, 9.999, 0.0), world.len() + 5).len(), world.len());
+
+        //tiles have to divide the world evenly and fit in the bounds around their centre
+        assert!(matches!(GeographicWorld::<()>::new().try_with_tile_degrees(0.7), Err(GeographicArrayError::InvalidTileSize { .. })));
+        assert!(GeographicWorld::<()>::with_tile_config(GeographicArrayConfig::default()).try_with_tile_degrees(2.0).is_err());
+        assert_eq!(GeographicWorld::<()>::new().with_tile_degrees(2.0).tile_config(), GeographicWorld::<()>::default_tile_config(2.0));
+        assert_eq!(GeographicWorld::<()>::new().with_tile_degrees(1.0).tile_degrees(), 1.0);
+        assert!(world.insert(&Geodetic::new(0.0, 0.0, 0.0), String::new()).tile.row > 0);
+    }
 }
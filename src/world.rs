@@ -0,0 +1,360 @@
+use crate::{Vector, Axis, Bounds, GeographicArrayConfig, GeographicArrayError, EntryId, QueryHit, QueryOptions, OutOfBoundsPolicy, geographic_array::GeographicArray, geodesy::Geodetic};
+
+use std::collections::HashMap;
+
+//tiles are this many degrees of latitude and longitude, half a degree is at most 56km across
+pub const WORLD_TILE_DEGREES: f64 = 0.5;
+//a tile is made for every square that gets a point, so the default tile config keeps its zones few and wide
+pub const WORLD_TILE_ZONE_WIDTH: f64 = 256.0;
+pub const WORLD_TILE_ALTITUDE_ZONE_WIDTH: f64 = 1000.0;
+//from the deepest ocean to stratospheric balloons, anything higher is clamped into the top zones and still found
+pub const WORLD_TILE_ALTITUDES: (f64, f64) = (-12000.0, 50000.0);
+
+//the most meters a degree of latitude or longitude can be, at the poles and the equator
+const MAX_METERS_PER_DEGREE_OF_LATITUDE: f64 = 111694.0;
+const MAX_METERS_PER_DEGREE_OF_LONGITUDE: f64 = 111320.0;
+//nothing stored is closer to the centre of the earth than this less the lowest altitude, the polar radius less a margin
+const MIN_EARTH_RADIUS: f64 = 6356000.0;
+
+//which tile of the world, rows count north from 90 south and columns east from 180 west
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TileKey {
+    pub row: u32,
+    pub column: u32,
+}
+
+//handle to a point stored in a GeographicWorld, the tile it is in and its handle there
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct WorldEntryId {
+    pub tile: TileKey,
+    pub id: EntryId,
+}
+
+//a hit from any tile, the distance is the straight line in meters, the same whichever tile it came from
+#[derive(PartialEq, Debug)]
+pub struct WorldHit<'a, T> {
+    tile: TileKey,
+    hit: QueryHit<'a, T>,
+}
+
+impl<'a, T> WorldHit<'a, T> {
+    pub fn distance(&self) -> f64 {
+        self.hit.distance()
+    }
+
+    pub fn id(&self) -> WorldEntryId {
+        WorldEntryId { tile: self.tile, id: self.hit.id() }
+    }
+
+    pub fn geodetic(&self) -> Geodetic {
+        self.hit.geodetic().expect("every tile has a geodetic origin")
+    }
+
+    pub fn value(&self) -> &'a T {
+        self.hit.value()
+    }
+
+    //the hit in its tile, the vector is in that tile's frame
+    pub fn hit(&self) -> &QueryHit<'a, T> {
+        &self.hit
+    }
+}
+
+impl<T> Clone for WorldHit<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            tile: self.tile,
+            hit: self.hit.clone(),
+        }
+    }
+}
+
+//GeographicArrays covering the whole world, each a tile of latitude and longitude with its own origin at its centre
+//tiles are made on the first insert into them, queries search every tile they could reach and merge the results
+pub struct GeographicWorld<T> {
+    tiles: HashMap<TileKey, GeographicArray<T>>,
+    tile_config: Option<GeographicArrayConfig>,    //None sizes the tiles with default_tile_config
+    tile_degrees: f64,
+    lowest_altitude: f64,   //of anything inserted, kept after removes, it only has to be a lower bound
+    len: usize,
+}
+
+impl<T> GeographicWorld<T> {
+    pub fn new() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            tile_config: None,
+            tile_degrees: WORLD_TILE_DEGREES,
+            lowest_altitude: f64::INFINITY,
+            len: 0,
+        }
+    }
+
+    //every tile is made with this config, its bounds have to reach the edges of a tile
+    //points outside the bounds are clamped into the edge zones, so it decides how fast queries are rather than what can be stored
+    pub fn with_tile_config(tile_config: GeographicArrayConfig) -> Self {
+        Self::try_with_tile_config(tile_config).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_with_tile_config(tile_config: GeographicArrayConfig) -> Result<Self, GeographicArrayError> {
+        tile_config.validate()?;
+        validate_tile(&tile_config, WORLD_TILE_DEGREES)?;
+        Ok(Self {
+            tile_config: Some(tile_config),
+            ..Self::new()
+        })
+    }
+
+    //bounds just past the edges of a tile this size and WORLD_TILE_ALTITUDES, in zones of WORLD_TILE_ZONE_WIDTH and WORLD_TILE_ALTITUDE_ZONE_WIDTH
+    //a half degree tile is about 13KB of zones
+    pub fn default_tile_config(tile_degrees: f64) -> GeographicArrayConfig {
+        let half: f64 = tile_degrees / 2.0 * MAX_METERS_PER_DEGREE_OF_LATITUDE * 1.02;
+        let zones: usize = (2.0 * half / WORLD_TILE_ZONE_WIDTH).ceil() as usize;
+        let (lowest, highest) = WORLD_TILE_ALTITUDES;
+        let altitude_zones: usize = ((highest - lowest) / WORLD_TILE_ALTITUDE_ZONE_WIDTH).ceil() as usize;
+        GeographicArrayConfig::with_zones(Bounds::new((-half, half), (-half, half), WORLD_TILE_ALTITUDES), zones, zones, altitude_zones)
+    }
+
+    pub fn tile_config(&self) -> GeographicArrayConfig {
+        self.tile_config.unwrap_or_else(|| Self::default_tile_config(self.tile_degrees))
+    }
+
+    pub fn with_tile_degrees(self, tile_degrees: f64) -> Self {
+        self.try_with_tile_degrees(tile_degrees).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    //tile_degrees has to divide 180 evenly, and a tile has to fit in the bounds of a config given with with_tile_config
+    //only before the first insert, the tiles already made would be keyed by the old size
+    pub fn try_with_tile_degrees(mut self, tile_degrees: f64) -> Result<Self, GeographicArrayError> {
+        if !self.tiles.is_empty() {
+            return Err(GeographicArrayError::InvalidTileSize { degrees: tile_degrees });
+        }
+        validate_tile(&self.tile_config.unwrap_or_else(|| Self::default_tile_config(tile_degrees)), tile_degrees)?;
+        self.tile_degrees = tile_degrees;
+        Ok(self)
+    }
+
+    pub fn tile_degrees(&self) -> f64 {
+        self.tile_degrees
+    }
+
+    fn rows(&self) -> u32 {
+        (180.0 / self.tile_degrees).round() as u32
+    }
+
+    fn columns(&self) -> u32 {
+        (360.0 / self.tile_degrees).round() as u32
+    }
+
+    //90 north is in the top row, 180 east is 180 west
+    pub fn tile_key(&self, geodetic: &Geodetic) -> TileKey {
+        TileKey {
+            row: (((geodetic.latitude + 90.0) / self.tile_degrees).floor() as i64).clamp(0, self.rows() as i64 - 1) as u32,
+            column: (((geodetic.longitude + 180.0) / self.tile_degrees).floor() as i64).rem_euclid(self.columns() as i64) as u32,
+        }
+    }
+
+    //the origin of the tile, at its centre on the ellipsoid
+    pub fn tile_origin(&self, key: &TileKey) -> Geodetic {
+        Geodetic {
+            latitude: -90.0 + (key.row as f64 + 0.5) * self.tile_degrees,
+            longitude: -180.0 + (key.column as f64 + 0.5) * self.tile_degrees,
+            altitude: 0.0,
+        }
+    }
+
+    pub fn tile(&self, key: &TileKey) -> Option<&GeographicArray<T>> {
+        self.tiles.get(key)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = (&TileKey, &GeographicArray<T>)> {
+        self.tiles.iter()
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, geodetic: &Geodetic, value: T) -> WorldEntryId {
+        self.try_insert(geodetic, value).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    //the tile is made if this is the first point in it
+    //any altitude can be stored, aircraft and satellites above the tile bounds are clamped into its top zones
+    pub fn try_insert(&mut self, geodetic: &Geodetic, value: T) -> Result<WorldEntryId, GeographicArrayError> {
+        geodetic.validate()?;
+        let key: TileKey = self.tile_key(geodetic);
+        let origin: Geodetic = self.tile_origin(&key);
+        let tile_config: GeographicArrayConfig = self.tile_config();
+        let tile: &mut GeographicArray<T> = self.tiles.entry(key).or_insert_with(|| {
+            GeographicArray::with_config(tile_config)
+                .with_out_of_bounds_policy(OutOfBoundsPolicy::Clamp)
+                .with_geodetic_origin(origin.latitude, origin.longitude, origin.altitude)
+        });
+        let id: EntryId = tile.try_insert_geodetic(geodetic, value)?;
+        self.lowest_altitude = self.lowest_altitude.min(geodetic.altitude);
+        self.len += 1;
+        Ok(WorldEntryId { tile: key, id })
+    }
+
+    //empty tiles are kept, they are made again on the next insert anyway
+    pub fn remove(&mut self, id: WorldEntryId) -> Option<(Geodetic, T)> {
+        let tile: &mut GeographicArray<T> = self.tiles.get_mut(&id.tile)?;
+        let (_, vector, value) = tile.remove(id.id)?;
+        self.len -= 1;
+        Some((tile.vector_to_geodetic(&vector).ok()?, value))
+    }
+
+    pub fn get(&self, id: WorldEntryId) -> Option<Geodetic> {
+        self.tiles.get(&id.tile)?.get_geodetic(id.id)
+    }
+
+    pub fn get_value(&self, id: WorldEntryId) -> Option<&T> {
+        self.tiles.get(&id.tile)?.get_value(id.id)
+    }
+
+    pub fn contains(&self, id: WorldEntryId) -> bool {
+        self.tiles.get(&id.tile).is_some_and(|tile| tile.contains(id.id))
+    }
+
+    //every point within radius meters in a straight line, ordered by distance, whichever tiles they are in
+    pub fn find_within_radius(&self, centre: &Geodetic, radius: f64) -> Vec<WorldHit<'_, T>> {
+        self.try_find_within_radius(centre, radius).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_find_within_radius(&self, centre: &Geodetic, radius: f64) -> Result<Vec<WorldHit<'_, T>>, GeographicArrayError> {
+        if !radius.is_finite() || radius < 0.0 {
+            return Err(GeographicArrayError::InvalidRadius(radius));
+        }
+        let mut hits: Vec<WorldHit<'_, T>> = Vec::new();
+        for key in self.tiles_within(centre, radius)? {
+            let tile: &GeographicArray<T> = &self.tiles[&key];
+            let vector: Vector = tile.geodetic_to_vector(centre)?;
+            hits.extend(tile.find_within_radius(&vector, radius).into_iter().map(|hit| WorldHit { tile: key, hit }));
+        }
+        sort(&mut hits);
+        Ok(hits)
+    }
+
+    //the closest stored point, however far away it is
+    pub fn find_nearest(&self, nearest_to: &Geodetic) -> Option<WorldHit<'_, T>> {
+        self.find_k_nearest(nearest_to, 1).into_iter().next()
+    }
+
+    //exactly the k closest, ordered by distance, fewer if the world holds less than k
+    pub fn find_k_nearest(&self, nearest_to: &Geodetic, k: usize) -> Vec<WorldHit<'_, T>> {
+        self.try_find_k_nearest(nearest_to, k).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    //searches every tile a radius could reach, doubling it until k are found within it
+    //each tile gives its own k closest within the radius, so the k closest overall are among them
+    pub fn try_find_k_nearest(&self, nearest_to: &Geodetic, k: usize) -> Result<Vec<WorldHit<'_, T>>, GeographicArrayError> {
+        nearest_to.validate()?;
+        if k == 0 || self.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut radius: f64 = self.tile_degrees * MAX_METERS_PER_DEGREE_OF_LATITUDE / 2.0;
+        loop {
+            let mut hits: Vec<WorldHit<'_, T>> = Vec::new();
+            let keys: Vec<TileKey> = self.tiles_within(nearest_to, radius)?;
+            let every_tile: bool = keys.len() == self.tiles.len();
+            for key in keys {
+                let tile: &GeographicArray<T> = &self.tiles[&key];
+                let vector: Vector = tile.geodetic_to_vector(nearest_to)?;
+                hits.extend(tile.find_k_nearest_with(&vector, k, &QueryOptions::new().max_distance(radius)).into_iter().map(|hit| WorldHit { tile: key, hit }));
+            }
+            sort(&mut hits);
+            //either k were found within the radius, or the radius already reaches everything there is
+            if hits.len() >= k || (every_tile && hits.len() == self.len) {
+                hits.truncate(k);
+                return Ok(hits);
+            }
+            radius *= 2.0;
+        }
+    }
+
+    //the tiles that have been made and could hold a point within radius meters of centre
+    fn tiles_within(&self, centre: &Geodetic, radius: f64) -> Result<Vec<TileKey>, GeographicArrayError> {
+        centre.validate()?;
+        //two points a chord apart can't be more than this angle apart as seen from the centre of the earth
+        //geodetic latitude can differ from that angle by about 0.7%, the margin covers it
+        let lowest: f64 = MIN_EARTH_RADIUS + self.lowest_altitude.min(centre.altitude);
+        let chord: f64 = radius / (2.0 * lowest);
+        if lowest <= 0.0 || chord >= 1.0 {
+            return Ok(self.tiles.keys().copied().collect());
+        }
+        let angle: f64 = (2.0 * chord.asin()).to_degrees() * 1.01 + 0.001;
+        let (south, north) = (centre.latitude - angle, centre.latitude + angle);
+
+        //near a pole a small circle can cover every longitude
+        let furthest_latitude: f64 = south.abs().max(north.abs());
+        let longitude_angle: Option<f64> = if furthest_latitude >= 90.0 {
+            None
+        } else {
+            let sin: f64 = angle.to_radians().sin() / furthest_latitude.to_radians().cos();
+            if sin >= 1.0 { None } else { Some(sin.asin().to_degrees() * 1.01 + 0.001) }
+        };
+        let row_range = (self.tile_key(&Geodetic { latitude: south.max(-90.0), ..*centre }).row, self.tile_key(&Geodetic { latitude: north.min(90.0), ..*centre }).row);
+        let columns: u32 = self.columns();
+        let column_range: Option<(u32, u32)> = longitude_angle.and_then(|longitude_angle| {
+            let west: i64 = ((centre.longitude - longitude_angle + 180.0) / self.tile_degrees).floor() as i64;
+            let east: i64 = ((centre.longitude + longitude_angle + 180.0) / self.tile_degrees).floor() as i64;
+            //the first column and how many from there, None if that wraps all the way round
+            (east - west + 1 < columns as i64).then(|| (west.rem_euclid(columns as i64) as u32, (east - west) as u32))
+        });
+        let within = |key: &TileKey| -> bool {
+            key.row >= row_range.0 && key.row <= row_range.1 && column_range.is_none_or(|(west, span)| (key.column + columns - west) % columns <= span)
+        };
+
+        //whichever is fewer, the tiles in range or the tiles that have been made
+        let in_range: u64 = (row_range.1 - row_range.0 + 1) as u64 * column_range.map_or(columns as u64, |(_, span)| span as u64 + 1);
+        let mut keys: Vec<TileKey> = if in_range <= self.tiles.len() as u64 {
+            (row_range.0..=row_range.1)
+                .flat_map(|row| match column_range {
+                    Some((west, span)) => (0..=span).map(|offset| (west + offset) % columns).collect::<Vec<u32>>(),
+                    None => (0..columns).collect(),
+                }.into_iter().map(move |column| TileKey { row, column }))
+                .filter(|key| self.tiles.contains_key(key))
+                .collect()
+        } else {
+            self.tiles.keys().copied().filter(within).collect()
+        };
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+impl<T> Default for GeographicWorld<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//closest first, ties in the order the tiles and entries were made
+fn sort<T>(hits: &mut [WorldHit<'_, T>]) {
+    hits.sort_by(|one, two| one.distance().total_cmp(&two.distance()).then_with(|| one.id().cmp(&two.id())));
+}
+
+//a tile has to divide the world evenly and every point in it has to be within the bounds around its centre
+fn validate_tile(tile_config: &GeographicArrayConfig, tile_degrees: f64) -> Result<(), GeographicArrayError> {
+    let invalid = GeographicArrayError::InvalidTileSize { degrees: tile_degrees };
+    if !tile_degrees.is_finite() || tile_degrees <= 0.0 || tile_degrees > 180.0 || ((180.0 / tile_degrees) - (180.0 / tile_degrees).round()).abs() > 1e-9 {
+        return Err(invalid);
+    }
+    for (axis, meters_per_degree) in [(Axis::X, MAX_METERS_PER_DEGREE_OF_LONGITUDE), (Axis::Y, MAX_METERS_PER_DEGREE_OF_LATITUDE)] {
+        let half: f64 = tile_degrees / 2.0 * meters_per_degree * 1.01;
+        let (min, max) = tile_config.bounds.axis(&axis);
+        if min > -half || max < half {
+            return Err(invalid);
+        }
+    }
+    Ok(())
+}